@@ -1,6 +1,9 @@
-use std::io::{Cursor, Write};
+use std::env::args_os;
+use std::io::{self as std_io, Cursor, Write};
 use std::mem::forget;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use pyo3::types::PyDict;
 use pyo3::{pyfunction, Py, PyAny, PyResult, Python};
@@ -12,7 +15,28 @@ use rustix::io::{write, Errno};
 use rustix::process::getpid;
 use rustix::{io, thread};
 
-use crate::ExtraSafeError;
+use crate::{ApplyError, ExtraSafeError};
+
+#[pyfunction]
+pub(crate) fn apply_strict_mode() -> PyResult<()> {
+    let mode = libc::SECCOMP_MODE_STRICT as libc::c_ulong;
+    if unsafe { libc::prctl(libc::PR_SET_SECCOMP, mode) } != 0 {
+        let err = std_io::Error::last_os_error();
+        return Err(ApplyError::new_err(format!(
+            "could not apply strict mode: {err}"
+        )));
+    }
+    Ok(())
+}
+
+#[pyfunction]
+pub(crate) fn explain_scope() -> &'static str {
+    "Seccomp filters are enforced by the kernel for every syscall of a thread. They do not \
+    distinguish between syscalls made by the Python interpreter, and syscalls made by native \
+    extensions, e.g. via ctypes or numpy. A filter applied to the current thread covers all code \
+    running in this thread, a filter applied to all threads covers all code in the process. \
+    Filters are inherited by new threads and child processes, and cannot be removed."
+}
 
 #[pyfunction]
 pub(crate) fn restrict_privileges() {
@@ -23,10 +47,17 @@ pub(crate) fn restrict_privileges() {
     let _: Result<(), io::Errno> = thread::set_no_new_privs(true);
 }
 
+static WARN_ON_LEAKED_LOCKS: AtomicBool = AtomicBool::new(false);
+
+#[pyfunction]
+pub(crate) fn warn_on_leaked_locks(enabled: bool) {
+    WARN_ON_LEAKED_LOCKS.store(enabled, Ordering::Relaxed);
+}
+
 #[pyfunction]
 #[pyo3(
-    signature = (path, *, closefd=false, cloexec=true, mode=0o640, contents=None),
-    text_signature = "(path, *, closefd=False, cloexec=True, mode=416, contents=None)"
+    signature = (path, *, closefd=false, cloexec=true, mode=0o640, contents=None, metadata=None),
+    text_signature = "(path, *, closefd=False, cloexec=True, mode=416, contents=None, metadata=None)"
 )]
 pub(crate) fn lock_pid_file(
     py: Python<'_>,
@@ -35,13 +66,18 @@ pub(crate) fn lock_pid_file(
     cloexec: bool,
     mode: RawMode,
     contents: Option<&[u8]>,
+    metadata: Option<&PyDict>,
 ) -> PyResult<Py<PyAny>> {
     let mode = Mode::from_bits(mode)
         .ok_or_else(|| ExtraSafeError::new_err("`mode` argument contains unknown bits"))?;
 
     let mut buffer;
+    let text;
     let contents = if let Some(contents) = contents {
         contents
+    } else if let Some(metadata) = metadata {
+        text = pid_file_metadata(py, metadata)?;
+        text.as_bytes()
     } else {
         buffer = [0u8; 24];
         let mut cursor = Cursor::new(&mut buffer[..]);
@@ -57,6 +93,51 @@ pub(crate) fn lock_pid_file(
     }
 }
 
+/// The PID, followed by the lines `start=<timestamp>` and `cmd=<argv0>`, and a `key=value` line
+/// for each other item of `metadata`. Items named `start` or `cmd` replace the defaults.
+fn pid_file_metadata(py: Python<'_>, metadata: &PyDict) -> PyResult<String> {
+    let start = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let argv = py.import("sys")?.getattr("argv");
+    let cmd = match argv.and_then(|argv| argv.get_item(0)) {
+        Ok(argv0) => argv0.str()?.to_str()?.to_owned(),
+        Err(_) => args_os()
+            .next()
+            .map(|argv0| argv0.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    // A program name is not checked like the metadata, so newlines are escaped.
+    let mut items = vec![
+        ("start".to_owned(), start.to_string()),
+        ("cmd".to_owned(), cmd.replace('\n', "\\n")),
+    ];
+
+    for (key, value) in metadata {
+        let key = key.str()?.to_str()?;
+        let value = value.str()?.to_str()?;
+        if key.is_empty() || key.contains(['=', '\n']) {
+            return Err(ExtraSafeError::new_err(format!(
+                "illegal metadata key {key:?}"
+            )));
+        } else if value.contains('\n') {
+            return Err(ExtraSafeError::new_err(format!(
+                "illegal metadata value {value:?}"
+            )));
+        }
+        match items.iter_mut().find(|(name, _)| name == key) {
+            Some((_, default)) => value.clone_into(default),
+            None => items.push((key.to_owned(), value.to_owned())),
+        }
+    }
+
+    let mut text = format!("{}\n", getpid().as_raw_nonzero().get());
+    for (key, value) in items {
+        text.push_str(&format!("{key}={value}\n"));
+    }
+    Ok(text)
+}
+
 fn raise_errno(py: Python<'_>, errno: Option<Errno>, msg: &str) -> PyResult<Py<PyAny>> {
     if errno == Some(Errno::INTR) {
         py.check_signals()?;
@@ -84,6 +165,22 @@ fn wrap_fd(py: Python<'_>, owned_fd: OwnedFd, closefd: bool) -> PyResult<Py<PyAn
     )?;
     let Some(file) = locals.get_item("ret") else { unreachable!() };
 
+    if !closefd && WARN_ON_LEAKED_LOCKS.load(Ordering::Relaxed) {
+        locals.set_item(
+            "msg",
+            format!(
+                "leaked the file descriptor {} of a locked PID file",
+                owned_fd.as_raw_fd()
+            ),
+        )?;
+        py.run(
+            "import warnings, weakref\n\
+            weakref.finalize(ret, warnings.warn, msg, ResourceWarning).atexit = False",
+            None,
+            Some(locals),
+        )?;
+    }
+
     forget(owned_fd);
     Ok(file.into())
 }
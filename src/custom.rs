@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use std::fmt::{self, Write};
 use std::hash::{Hash, Hasher};
 
-use extrasafe::{Rule, RuleSet, SafetyContext};
+use extrasafe::{Rule, RuleSet};
 use libseccomp::{ScmpArgCompare, ScmpCompareOp};
 use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::pyclass::CompareOp;
@@ -11,6 +11,7 @@ use pyo3::types::PyDict;
 use pyo3::{pyclass, pymethods, FromPyObject, Py, PyAny, PyCell, PyRefMut, PyResult, Python};
 use syscalls::Sysno;
 
+use crate::filter::Filter;
 use crate::rule_sets::{DataRuleSet, EnablePolicy, PyRuleSet};
 use crate::ExtraSafeError;
 
@@ -29,8 +30,8 @@ pub(crate) struct DataCustom {
 }
 
 impl EnablePolicy for DataCustom {
-    fn enable_to(&self, ctx: SafetyContext) -> Result<SafetyContext, extrasafe::ExtraSafeError> {
-        ctx.enable(self)
+    fn enable_to(&self, filter: &mut Filter) -> Result<(), extrasafe::ExtraSafeError> {
+        filter.enable(self)
     }
 }
 
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::mem::size_of;
+use std::path::PathBuf;
+
+use extrasafe::builtins::BasicCapabilities;
+use extrasafe::{ExtraSafeError, Rule, RuleSet};
+use libseccomp::{ScmpAction, ScmpArch, ScmpFilterContext};
+use rustix::fs::{memfd_create, MemfdFlags};
+use rustix::io::Errno;
+use syscalls::Sysno;
+
+/// The rules of an [`extrasafe::SafetyContext`], but accessible.
+///
+/// `extrasafe` does not give access to the compiled seccomp filter, so we have to compile it
+/// ourselves. The rules are kept ordered by syscall, so the compiled filter is deterministic.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Filter {
+    rules: BTreeMap<Sysno, Vec<(&'static str, Rule)>>,
+    /// Paths for a Landlock ruleset, if the filesystem access should be restricted.
+    pub(crate) landlock_paths: Option<Vec<PathBuf>>,
+    /// The action for syscalls without a matching rule, by default `Errno(EPERM)`.
+    pub(crate) default_action: Option<ScmpAction>,
+    /// Don't enable [`BasicCapabilities`] implicitly.
+    pub(crate) without_basics: bool,
+    /// Syscalls that kill the process, even if a rule allows them.
+    pub(crate) forbidden: Vec<Sysno>,
+    /// Syscalls that are decided by a supervisor, even if a rule allows them.
+    pub(crate) notified: Vec<Sysno>,
+    /// Raise `SIGSYS` instead of killing, so that a signal handler can tell the syscall.
+    pub(crate) trap_violations: bool,
+}
+
+impl Filter {
+    /// Only grant filesystem access beneath `paths`. Multiple calls grant access to the union.
+    pub(crate) fn restrict_paths(&mut self, paths: &[PathBuf]) {
+        let landlock_paths = self.landlock_paths.get_or_insert_with(Vec::new);
+        for path in paths {
+            if !landlock_paths.contains(path) {
+                landlock_paths.push(path.clone());
+            }
+        }
+    }
+
+    /// The syscalls that have rules, and if all of their rules are conditional.
+    pub(crate) fn syscalls(&self) -> Vec<(Sysno, bool)> {
+        self.rules
+            .iter()
+            .filter(|(sysno, _)| !self.forbidden.contains(sysno) && !self.notified.contains(sysno))
+            .map(|(&sysno, rules)| {
+                let conditional = rules.iter().all(|(_, rule)| !rule.comparators.is_empty());
+                (sysno, conditional)
+            })
+            .collect()
+    }
+
+    /// The syscalls that the compiled filter has rules for, including [`BasicCapabilities`].
+    pub(crate) fn allowed_syscalls(mut self) -> Result<Vec<Sysno>, ExtraSafeError> {
+        if !self.without_basics {
+            self.enable(BasicCapabilities)?;
+        }
+        Ok(self
+            .syscalls()
+            .into_iter()
+            .map(|(sysno, _)| sysno)
+            .collect())
+    }
+
+    /// Same as [`extrasafe::SafetyContext::enable()`], but identical rules are only added once.
+    pub(crate) fn enable(&mut self, policy: impl RuleSet) -> Result<(), ExtraSafeError> {
+        self.enable_rules(policy.name(), gather_rules(&policy))
+    }
+
+    /// Like [`Filter::enable()`], but conditional rules are skipped if their syscall is allowed
+    /// unconditionally already.
+    pub(crate) fn enable_unless_allowed(
+        &mut self,
+        policy: impl RuleSet,
+    ) -> Result<(), ExtraSafeError> {
+        let mut rules = gather_rules(&policy);
+        rules.retain(|rule| {
+            rule.comparators.is_empty() || !self.is_allowed_unconditionally(rule.syscall)
+        });
+        self.enable_rules(policy.name(), rules)
+    }
+
+    fn is_allowed_unconditionally(&self, sysno: Sysno) -> bool {
+        self.rules
+            .get(&sysno)
+            .is_some_and(|rules| rules.iter().any(|(_, rule)| rule.comparators.is_empty()))
+    }
+
+    fn enable_rules(
+        &mut self,
+        policy_name: &'static str,
+        rules: Vec<Rule>,
+    ) -> Result<(), ExtraSafeError> {
+        for new_rule in rules {
+            let existing_rules = self.rules.entry(new_rule.syscall).or_default();
+            // E.g. a file descriptor that is registered in the context and in a SystemIO, too.
+            if existing_rules
+                .iter()
+                .any(|(_, rule)| rule.comparators == new_rule.comparators)
+            {
+                continue;
+            }
+            for &(existing_name, ref existing_rule) in existing_rules.iter() {
+                let new_is_simple = new_rule.comparators.is_empty();
+                let existing_is_simple = existing_rule.comparators.is_empty();
+                if new_is_simple && !existing_is_simple {
+                    return Err(ExtraSafeError::ConditionalNoEffectError(
+                        new_rule.syscall,
+                        existing_name,
+                        policy_name,
+                    ));
+                } else if !new_is_simple && existing_is_simple {
+                    return Err(ExtraSafeError::ConditionalNoEffectError(
+                        new_rule.syscall,
+                        policy_name,
+                        existing_name,
+                    ));
+                }
+            }
+            existing_rules.push((policy_name, new_rule));
+        }
+        Ok(())
+    }
+
+    /// Check that every syscall that this filter allows is allowed by `other`, too.
+    ///
+    /// Conditional rules are compared conservatively: a rule is covered by a rule of `other`, if
+    /// it has all of the other rule's comparators, so it is at least as narrow.
+    pub(crate) fn covered_by(mut self, mut other: Filter) -> Result<bool, ExtraSafeError> {
+        for filter in [&mut self, &mut other] {
+            if !filter.without_basics {
+                filter.enable(BasicCapabilities)?;
+            }
+        }
+
+        if let Some(other_paths) = &other.landlock_paths {
+            let Some(paths) = &self.landlock_paths else {
+                return Ok(false);
+            };
+            if !paths
+                .iter()
+                .all(|path| other_paths.iter().any(|other| path.starts_with(other)))
+            {
+                return Ok(false);
+            }
+        }
+
+        // A supervisor might allow any notified syscall.
+        for sysno in &self.notified {
+            if !self.forbidden.contains(sysno)
+                && !other.notified.contains(sysno)
+                && (other.forbidden.contains(sysno) || !other.is_allowed_unconditionally(*sysno))
+            {
+                return Ok(false);
+            }
+        }
+
+        for (sysno, rules) in &self.rules {
+            if self.forbidden.contains(sysno) || self.notified.contains(sysno) {
+                continue;
+            }
+            let Some(other_rules) = other.rules.get(sysno) else {
+                return Ok(false);
+            };
+            if other.forbidden.contains(sysno) || other.notified.contains(sysno) {
+                return Ok(false);
+            } else if other.is_allowed_unconditionally(*sysno) {
+                continue;
+            }
+            let covered = rules.iter().all(|(_, rule)| {
+                other_rules.iter().any(|(_, other)| {
+                    other
+                        .comparators
+                        .iter()
+                        .all(|cmp| rule.comparators.contains(cmp))
+                })
+            });
+            if !covered {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Same as `extrasafe::SafetyContext::apply()`, but the filter is returned instead of loaded.
+    pub(crate) fn compile(
+        mut self,
+        all_threads: bool,
+    ) -> Result<ScmpFilterContext, ExtraSafeError> {
+        let default_action = self
+            .default_action
+            .unwrap_or(ScmpAction::Errno(Errno::PERM.raw_os_error()));
+        let (default_action, kill_action) = match self.trap_violations {
+            true => match default_action {
+                ScmpAction::KillThread | ScmpAction::KillProcess => {
+                    (ScmpAction::Trap, ScmpAction::Trap)
+                },
+                default_action => (default_action, ScmpAction::Trap),
+            },
+            false => (default_action, ScmpAction::KillProcess),
+        };
+        let mut ctx = ScmpFilterContext::new_filter(default_action)?;
+        ctx.set_ctl_tsync(all_threads)?;
+        let _: bool = ctx.add_arch(ScmpArch::Native)?;
+
+        if !self.without_basics {
+            self.enable(BasicCapabilities)?;
+        }
+        for (_, rule) in self.rules.into_values().flatten() {
+            if self.forbidden.contains(&rule.syscall) || self.notified.contains(&rule.syscall) {
+                continue;
+            } else if rule.comparators.is_empty() {
+                ctx.add_rule(ScmpAction::Allow, rule.syscall.id())?;
+            } else {
+                ctx.add_rule_conditional(ScmpAction::Allow, rule.syscall.id(), &rule.comparators)?;
+            }
+        }
+        for &sysno in &self.notified {
+            if !self.forbidden.contains(&sysno) {
+                ctx.add_rule(ScmpAction::Notify, sysno.id())?;
+            }
+        }
+        for sysno in self.forbidden {
+            ctx.add_rule(kill_action, sysno.id())?;
+        }
+        Ok(ctx)
+    }
+}
+
+/// Same as `extrasafe::SafetyContext::gather_rules()`.
+fn gather_rules(policy: &impl RuleSet) -> Vec<Rule> {
+    let mut rules = policy.conditional_rules();
+    for syscall in policy.simple_rules() {
+        let _: &mut Vec<Rule> = rules
+            .entry(syscall)
+            .or_insert_with(|| vec![Rule::new(syscall)]);
+    }
+    rules.into_values().flatten().collect()
+}
+
+/// Split an exported BPF program into its instructions.
+pub(crate) fn sock_filters(bpf: &[u8]) -> Vec<libc::sock_filter> {
+    bpf.chunks_exact(size_of::<libc::sock_filter>())
+        .map(|insn| libc::sock_filter {
+            code: u16::from_ne_bytes([insn[0], insn[1]]),
+            jt: insn[2],
+            jf: insn[3],
+            k: u32::from_ne_bytes([insn[4], insn[5], insn[6], insn[7]]),
+        })
+        .collect()
+}
+
+/// Get the BPF program, that [`ScmpFilterContext::load()`] would install.
+pub(crate) fn export_bpf(ctx: &ScmpFilterContext) -> io::Result<Vec<u8>> {
+    let mut file = File::from(memfd_create("pyextrasafe-bpf", MemfdFlags::CLOEXEC)?);
+    ctx.export_bpf(&mut file).map_err(io::Error::other)?;
+    file.rewind()?;
+
+    let mut bpf = Vec::new();
+    let _: usize = file.read_to_end(&mut bpf)?;
+    Ok(bpf)
+}
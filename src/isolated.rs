@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::mem::zeroed;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use extrasafe::builtins::SystemIO;
+use pyo3::types::{PyBytes, PyModule, PyTuple};
+use pyo3::{pyfunction, IntoPy, Py, PyAny, PyErr, PyObject, PyRef, PyResult, Python};
+use rustix::io::{pipe_with, PipeFlags};
+use syscalls::Sysno;
+
+use crate::safety_ctx::{load_filter, PySafetyContext};
+use crate::ExtraSafeError;
+
+/// The child sends this instead of a result, followed by the number of the forbidden syscall.
+const SIGSYS_MAGIC: [u8; 4] = *b"SYS\0";
+
+/// The pipe to the parent process, for the `SIGSYS` handler of the child.
+static REPORT_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// The start of the kernel's `siginfo_t` for `SIGSYS`, which the `libc` crate does not expose.
+#[repr(C)]
+struct SigsysInfo {
+    signo: libc::c_int,
+    errno: libc::c_int,
+    code: libc::c_int,
+    call_addr: *mut libc::c_void,
+    syscall: libc::c_int,
+    arch: libc::c_uint,
+}
+
+#[pyfunction]
+#[pyo3(signature = (context, func, *args))]
+pub(crate) fn run_isolated(
+    py: Python<'_>,
+    context: PyRef<'_, PySafetyContext>,
+    func: &PyAny,
+    args: &PyTuple,
+) -> PyResult<Py<PyAny>> {
+    let pickle = py.import("pickle")?;
+    let os = py.import("os")?;
+
+    let (reader, writer) = pipe_with(PipeFlags::CLOEXEC).map_err(|err| {
+        ExtraSafeError::new_err(format!(
+            "could not create a pipe for the child process: {err}"
+        ))
+    })?;
+
+    let pid: libc::pid_t = os.call_method0("fork")?.extract()?;
+    if pid == 0 {
+        drop(reader);
+        let code = match run_child(py, &context, func, args, pickle, writer) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        };
+        // Never return into the Python code of the parent process.
+        unsafe { libc::_exit(code) };
+    }
+    drop(writer);
+
+    let mut result = Vec::new();
+    let read_result = py.allow_threads(|| File::from(reader).read_to_end(&mut result));
+    let (_, status): (libc::pid_t, libc::c_int) =
+        os.call_method1("waitpid", (pid, 0))?.extract()?;
+
+    if libc::WIFSIGNALED(status) {
+        let msg = match libc::WTERMSIG(status) {
+            libc::SIGSYS => match forbidden_syscall(&result) {
+                Some(name) => {
+                    format!(
+                        "the child process was killed by SIGSYS for the forbidden syscall {name}"
+                    )
+                },
+                None => "the child process was killed by SIGSYS for a forbidden syscall".to_owned(),
+            },
+            signal => format!("the child process was killed by signal {signal}"),
+        };
+        return Err(ExtraSafeError::new_err(msg));
+    }
+    if let Err(err) = read_result {
+        return Err(ExtraSafeError::new_err(format!(
+            "could not read the result of the child process: {err}"
+        )));
+    }
+    if result.is_empty() {
+        return Err(ExtraSafeError::new_err(format!(
+            "the child process exited with status {} without returning a result",
+            libc::WEXITSTATUS(status),
+        )));
+    }
+
+    let result = pickle.call_method1("loads", (PyBytes::new(py, &result),))?;
+    let (success, value): (bool, &PyAny) = result.extract()?;
+    match success {
+        true => Ok(value.into()),
+        false => Err(PyErr::from_value(value)),
+    }
+}
+
+/// The name of the syscall that the `SIGSYS` handler of the child reported.
+fn forbidden_syscall(result: &[u8]) -> Option<String> {
+    let nr = result.strip_prefix(&SIGSYS_MAGIC)?;
+    let nr = libc::c_int::from_ne_bytes(nr.try_into().ok()?);
+    Some(match usize::try_from(nr).ok().and_then(Sysno::new) {
+        Some(sysno) => sysno.name().to_owned(),
+        None => format!("#{nr}"),
+    })
+}
+
+/// Report the forbidden syscall to the parent, then die from the `SIGSYS`.
+extern "C" fn report_sigsys(_: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    let info = unsafe { &*info.cast::<SigsysInfo>() };
+    let mut record = [0; 8];
+    record[..4].copy_from_slice(&SIGSYS_MAGIC);
+    record[4..].copy_from_slice(&info.syscall.to_ne_bytes());
+    unsafe {
+        let _: libc::ssize_t = libc::write(
+            REPORT_FD.load(Ordering::Relaxed),
+            record.as_ptr().cast(),
+            record.len(),
+        );
+        // The handler was reset, and SIGSYS is blocked until the handler returns. If raising the
+        // signal is forbidden, too, then the kernel kills the process immediately.
+        let _: libc::c_int = libc::raise(libc::SIGSYS);
+    }
+}
+
+/// Instead of simply killing the child, the filter traps the syscall, so it can be named.
+fn install_sigsys_handler(file: &File) -> PyResult<()> {
+    REPORT_FD.store(file.as_raw_fd(), Ordering::Relaxed);
+    let mut action: libc::sigaction = unsafe { zeroed() };
+    let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+        report_sigsys;
+    action.sa_sigaction = handler as libc::sighandler_t;
+    action.sa_flags = libc::SA_SIGINFO | libc::SA_RESETHAND;
+    match unsafe { libc::sigaction(libc::SIGSYS, &action, std::ptr::null_mut()) } {
+        0 => Ok(()),
+        _ => Err(ExtraSafeError::new_err(format!(
+            "could not install a SIGSYS handler: {}",
+            std::io::Error::last_os_error(),
+        ))),
+    }
+}
+
+fn run_child(
+    py: Python<'_>,
+    context: &PySafetyContext,
+    func: &PyAny,
+    args: &PyTuple,
+    pickle: &PyModule,
+    writer: OwnedFd,
+) -> PyResult<()> {
+    let mut file = File::from(writer);
+
+    let result = install_sigsys_handler(&file)
+        .and_then(|()| context.to_filter(py))
+        .and_then(|mut filter| {
+            // The child has to be able to report its result, even if the policies forbid writing.
+            let _: Result<(), _> =
+                filter.enable_unless_allowed(SystemIO::nothing().allow_file_write(&file));
+            filter.trap_violations = true;
+            load_filter(py, filter, false)
+        })
+        .and_then(|_| func.call1(args));
+    let outcome: (bool, PyObject) = match result {
+        Ok(value) => (true, value.into_py(py)),
+        Err(err) => (false, err.into_value(py).into_py(py)),
+    };
+
+    let data = match pickle.call_method1("dumps", (outcome,)) {
+        Ok(data) => data,
+        Err(err) => {
+            let err = ExtraSafeError::new_err(format!("could not pickle the result: {err}"));
+            pickle.call_method1("dumps", ((false, err.into_value(py)),))?
+        },
+    };
+    file.write_all(data.extract()?).map_err(|err| {
+        ExtraSafeError::new_err(format!(
+            "could not send the result to the parent process: {err}"
+        ))
+    })
+}
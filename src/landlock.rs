@@ -0,0 +1,121 @@
+//! Minimal bindings for [Landlock](https://docs.kernel.org/userspace-api/landlock.html).
+
+use std::io;
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::PathBuf;
+use std::ptr::null;
+
+use pyo3::PyResult;
+use rustix::fs::{cwd, fstat, openat, FileType, Mode, OFlags};
+use rustix::thread;
+
+use crate::ExtraSafeError;
+
+const CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+const RULE_PATH_BENEATH: libc::c_int = 1;
+
+/// All filesystem access rights of the first Landlock ABI version.
+const ACCESS_FS_ALL: u64 = (1 << 13) - 1;
+/// The access rights in [`ACCESS_FS_ALL`] that are applicable to files.
+const ACCESS_FS_FILE: u64 = (1 << 3) - 1;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: RawFd,
+}
+
+/// A Landlock ruleset that only grants filesystem access beneath some paths.
+#[derive(Debug)]
+pub(crate) struct Ruleset(OwnedFd);
+
+impl Ruleset {
+    pub(crate) fn new(paths: &[PathBuf]) -> PyResult<Self> {
+        let version = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if version < 1 {
+            let err = io::Error::last_os_error();
+            return Err(ExtraSafeError::new_err(format!(
+                "landlock is not available: {err}"
+            )));
+        }
+
+        let attr = RulesetAttr {
+            handled_access_fs: ACCESS_FS_ALL,
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        if fd < 0 {
+            let err = io::Error::last_os_error();
+            return Err(ExtraSafeError::new_err(format!(
+                "could not create landlock ruleset: {err}"
+            )));
+        }
+        let ruleset = Self(unsafe { OwnedFd::from_raw_fd(fd as RawFd) });
+
+        for path in paths {
+            let err = |err| {
+                ExtraSafeError::new_err(format!(
+                    "could not add {path:?} to landlock ruleset: {err}"
+                ))
+            };
+
+            let parent = openat(cwd(), path, OFlags::PATH | OFlags::CLOEXEC, Mode::empty())
+                .map_err(|e| err(io::Error::from(e)))?;
+            let stat = fstat(&parent).map_err(|e| err(io::Error::from(e)))?;
+            let allowed_access = match FileType::from_raw_mode(stat.st_mode) == FileType::Directory
+            {
+                true => ACCESS_FS_ALL,
+                false => ACCESS_FS_FILE,
+            };
+
+            let attr = PathBeneathAttr {
+                allowed_access,
+                parent_fd: parent.as_raw_fd(),
+            };
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.0.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &attr as *const PathBeneathAttr,
+                    0u32,
+                )
+            };
+            if result != 0 {
+                return Err(err(io::Error::last_os_error()));
+            }
+        }
+
+        Ok(ruleset)
+    }
+
+    /// Restrict the current thread. Landlock has no way to restrict other threads.
+    pub(crate) fn restrict_self(self) -> io::Result<()> {
+        thread::set_no_new_privs(true)?;
+        let result =
+            unsafe { libc::syscall(libc::SYS_landlock_restrict_self, self.0.as_raw_fd(), 0u32) };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
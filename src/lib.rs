@@ -38,12 +38,18 @@
 
 mod additional;
 // mod custom;
+mod filter;
+mod isolated;
+mod landlock;
+mod notify;
+mod probe;
+mod remote;
 mod rule_sets;
 mod safety_ctx;
 
 use pyo3::exceptions::PyException;
 use pyo3::types::PyModule;
-use pyo3::{pymodule, wrap_pyfunction, PyResult, PyTypeInfo, Python};
+use pyo3::{pymodule, wrap_pyfunction, Py, PyResult, PyTypeInfo, Python};
 
 pyo3::create_exception!(
     pyextrasafe,
@@ -52,6 +58,13 @@ pyo3::create_exception!(
     "An exception thrown by PyExtraSafe."
 );
 
+pyo3::create_exception!(
+    pyextrasafe,
+    ApplyError,
+    ExtraSafeError,
+    "A seccomp filter could not be applied."
+);
+
 #[pymodule]
 fn _pyextrasafe(py: Python<'_>, m: &PyModule) -> PyResult<()> {
     // m.add_class::<self::custom::PyCompare>()?;
@@ -59,19 +72,49 @@ fn _pyextrasafe(py: Python<'_>, m: &PyModule) -> PyResult<()> {
     // m.add_class::<self::custom::PyCustom>()?;
     // m.add_class::<self::custom::PyRule>()?;
     m.add_class::<self::rule_sets::PyBasicCapabilities>()?;
+    m.add_class::<self::rule_sets::PyCredentials>()?;
     m.add_class::<self::rule_sets::PyForkAndExec>()?;
+    m.add_class::<self::rule_sets::PyIoUring>()?;
+    m.add_class::<self::rule_sets::PyMountNamespace>()?;
     m.add_class::<self::rule_sets::PyNetworking>()?;
+    m.add_class::<self::rule_sets::PyPidFd>()?;
+    m.add_class::<self::rule_sets::PyProcessControl>()?;
     m.add_class::<self::rule_sets::PyRuleSet>()?;
+    m.add_class::<self::rule_sets::PyScheduling>()?;
     m.add_class::<self::rule_sets::PySystemIO>()?;
     m.add_class::<self::rule_sets::PyThreads>()?;
     m.add_class::<self::rule_sets::PyTime>()?;
     m.add_class::<self::safety_ctx::PySafetyContext>()?;
+    m.add_function(wrap_pyfunction!(self::additional::apply_strict_mode, m)?)?;
+    m.add_function(wrap_pyfunction!(self::additional::explain_scope, m)?)?;
     m.add_function(wrap_pyfunction!(self::additional::lock_pid_file, m)?)?;
     m.add_function(wrap_pyfunction!(self::additional::restrict_privileges, m)?)?;
+    m.add_function(wrap_pyfunction!(self::isolated::run_isolated, m)?)?;
+    m.add_function(wrap_pyfunction!(self::rule_sets::catalog, m)?)?;
+    m.add_function(wrap_pyfunction!(self::safety_ctx::apply_all, m)?)?;
+    m.add_function(wrap_pyfunction!(self::safety_ctx::apply_compute_only, m)?)?;
+    m.add_function(wrap_pyfunction!(self::safety_ctx::describe, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        self::safety_ctx::install_sitecustomize,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(self::safety_ctx::parse_seccomp_log, m)?)?;
+    m.add_function(wrap_pyfunction!(self::safety_ctx::reexec_sandboxed, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        self::safety_ctx::sandbox_thread_initializer,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(self::additional::warn_on_leaked_locks, m)?)?;
     m.add("__author__", env!("CARGO_PKG_AUTHORS"))?;
     m.add("__license__", env!("CARGO_PKG_LICENSE"))?;
     m.add("__version__", env!("pyextrasafe-version"))?;
     m.add("ExtraSafeError", ExtraSafeError::type_object(py))?;
+    m.add("ApplyError", ApplyError::type_object(py))?;
+    m.add("CONTINUE", Py::new(py, self::notify::PyContinue)?)?;
+    // Only set if the kernel rejected the filter, see `CompiledFilter::load()`.
+    for attr in ["errno", "flags", "bpf_len"] {
+        ApplyError::type_object(py).setattr(attr, py.None())?;
+    }
     // m.add("sysno", self::custom::make_syscall_dict(py)?)?;
     Ok(())
 }
@@ -0,0 +1,213 @@
+//! Let a Python function decide about syscalls, using seccomp's user notifications.
+//!
+//! The kernel suspends a thread that makes a notified syscall, until a supervisor answers through
+//! the notification file descriptor of the filter. The supervisor threads are started before the
+//! filter is loaded, so the filter does not restrict them.
+//!
+//! The Python handler needs the GIL, but the suspended thread might hold it. So one thread only
+//! receives the notifications and answers them, and another thread calls the handler. If the
+//! handler does not decide in time, the syscall fails with `EPERM`.
+//!
+//! Letting a syscall continue (`SECCOMP_USER_NOTIF_FLAG_CONTINUE`) needs an explicit decision of
+//! the handler, and must not be used to implement a security policy. The kernel reads the memory
+//! that pointer arguments refer to only after the decision, so another thread can change e.g. a
+//! path after the handler checked it.
+
+use std::io;
+use std::mem::zeroed;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use pyo3::types::PyTuple;
+use pyo3::{pyclass, pymethods, Py, PyAny, PyResult, Python};
+
+/// The decision of a handler to let the kernel execute a notified syscall.
+///
+/// Only this explicit value continues a syscall, so a handler that forgets to return something
+/// denies the syscall instead.
+#[pyclass(frozen)]
+#[pyo3(name = "_Continue", module = "pyextrasafe")]
+#[derive(Debug)]
+pub(crate) struct PyContinue;
+
+#[pymethods]
+impl PyContinue {
+    fn __repr__(&self) -> &'static str {
+        "pyextrasafe.CONTINUE"
+    }
+}
+
+/// What happens with a notified syscall.
+#[derive(Debug, Clone, Copy)]
+enum Decision {
+    /// Let the kernel execute the syscall.
+    Continue,
+    /// Let the syscall fail with this errno.
+    Errno(libc::c_int),
+}
+
+/// Start the supervisor for a filter that is not loaded yet. The notification file descriptor of
+/// the filter is sent to the supervisor, once the filter is loaded. If the sender is dropped
+/// instead, the supervisor stops.
+pub(crate) fn spawn_supervisor(
+    handler: Py<PyAny>,
+    timeout: Duration,
+) -> io::Result<SyncSender<OwnedFd>> {
+    let (fd_sender, fd_receiver) = sync_channel(1);
+    let _: JoinHandle<()> = thread::Builder::new()
+        .name("pyextrasafe-supervisor".to_owned())
+        .spawn(move || {
+            if let Ok(fd) = fd_receiver.recv() {
+                supervise(Arc::new(fd), handler, timeout);
+            }
+        })?;
+    Ok(fd_sender)
+}
+
+/// Answer the notifications until no thread uses the filter anymore.
+fn supervise(fd: Arc<OwnedFd>, handler: Py<PyAny>, timeout: Duration) {
+    let (request_sender, request_receiver) = channel();
+    let (decision_sender, decision_receiver) = channel();
+    let handler_fd = Arc::clone(&fd);
+    // If the thread cannot be started, then every notified syscall fails.
+    let _: io::Result<JoinHandle<()>> = thread::Builder::new()
+        .name("pyextrasafe-handler".to_owned())
+        .spawn(move || call_handler(&handler_fd, &handler, request_receiver, decision_sender));
+
+    while wait_for_request(&fd) {
+        // The thread that made the syscall could have been killed in the meantime.
+        let Ok(request) = receive(&fd) else {
+            continue;
+        };
+        let decision = match request_sender.send(request) {
+            Ok(()) => wait_for_decision(&decision_receiver, request.id, timeout),
+            Err(_) => Decision::Errno(libc::EPERM),
+        };
+        let _: io::Result<()> = respond(&fd, request.id, decision);
+    }
+}
+
+fn receive(fd: &OwnedFd) -> io::Result<libc::seccomp_notif> {
+    // The kernel rejects requests that are not zeroed.
+    let mut request: libc::seccomp_notif = unsafe { zeroed() };
+    loop {
+        let result =
+            unsafe { libc::ioctl(fd.as_raw_fd(), libc::SECCOMP_IOCTL_NOTIF_RECV, &mut request) };
+        match result {
+            0 => return Ok(request),
+            _ => match io::Error::last_os_error() {
+                err if err.kind() == io::ErrorKind::Interrupted => continue,
+                err => return Err(err),
+            },
+        }
+    }
+}
+
+fn respond(fd: &OwnedFd, id: u64, decision: Decision) -> io::Result<()> {
+    let (error, flags) = match decision {
+        Decision::Continue => (0, libc::SECCOMP_USER_NOTIF_FLAG_CONTINUE as u32),
+        Decision::Errno(errno) => (-errno, 0),
+    };
+    let mut response = libc::seccomp_notif_resp {
+        id,
+        val: 0,
+        error,
+        flags,
+    };
+    let result = unsafe {
+        libc::ioctl(
+            fd.as_raw_fd(),
+            libc::SECCOMP_IOCTL_NOTIF_SEND,
+            &mut response,
+        )
+    };
+    match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Returns `false` if the request was answered already, or its thread was killed.
+fn is_pending(fd: &OwnedFd, mut id: u64) -> bool {
+    unsafe { libc::ioctl(fd.as_raw_fd(), libc::SECCOMP_IOCTL_NOTIF_ID_VALID, &mut id) == 0 }
+}
+
+/// Returns `false` if no thread uses the filter anymore, so no request can arrive.
+fn wait_for_request(fd: &OwnedFd) -> bool {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    loop {
+        let result = unsafe { libc::poll(&mut pollfd, 1, -1) };
+        if result < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            continue;
+        }
+        return result > 0
+            && pollfd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) == 0;
+    }
+}
+
+fn wait_for_decision(
+    decisions: &Receiver<(u64, Decision)>,
+    id: u64,
+    timeout: Duration,
+) -> Decision {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match decisions.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok((decided_id, decision)) if decided_id == id => return decision,
+            // A late decision for a request that timed out.
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                return Decision::Errno(libc::EPERM);
+            },
+        }
+    }
+}
+
+fn call_handler(
+    fd: &OwnedFd,
+    handler: &Py<PyAny>,
+    requests: Receiver<libc::seccomp_notif>,
+    decisions: Sender<(u64, Decision)>,
+) {
+    for request in requests {
+        let decision = Python::with_gil(|py| {
+            // Don't call the handler for a request that timed out already.
+            if !is_pending(fd, request.id) {
+                return None;
+            }
+
+            let args = PyTuple::new(py, request.data.args);
+            let result = handler
+                .call1(py, (request.data.nr, args))
+                .and_then(|decision| decide(decision.as_ref(py)));
+            Some(result.unwrap_or_else(|err| {
+                err.print(py);
+                Decision::Errno(libc::EPERM)
+            }))
+        });
+        if let Some(decision) = decision {
+            if decisions.send((request.id, decision)).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Only [`PyContinue`] lets the syscall continue. `None`, e.g. from a missing `return`, and
+/// non-positive numbers deny it with `EPERM`.
+fn decide(decision: &PyAny) -> PyResult<Decision> {
+    if decision.is_instance_of::<PyContinue>() {
+        return Ok(Decision::Continue);
+    }
+    Ok(match decision.extract::<Option<libc::c_int>>()? {
+        Some(errno) if errno > 0 => Decision::Errno(errno),
+        _ => Decision::Errno(libc::EPERM),
+    })
+}
@@ -0,0 +1,241 @@
+//! Check filters in child processes, before they are installed for real.
+
+use std::io;
+use std::mem::size_of;
+use std::ptr::null_mut;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use libseccomp::ScmpAction;
+use pyo3::{PyResult, Python};
+use syscalls::Sysno;
+
+use crate::filter::{export_bpf, sock_filters, Filter};
+use crate::{ApplyError, ExtraSafeError};
+
+/// How long a probed syscall may block before it counts as allowed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The child's exit code if it could not install the filter.
+const EXIT_NOT_INSTALLED: libc::c_int = 2;
+
+/// Raise an [`ApplyError`] naming all `syscalls`, that `filter` does not deny.
+///
+/// The filter is compiled with [`ScmpAction::KillProcess`] as its default action, so that a denied
+/// syscall always terminates the child with `SIGSYS`, and cannot be confused with a syscall that
+/// failed on its own.
+pub(crate) fn assert_denied(
+    py: Python<'_>,
+    mut filter: Filter,
+    syscalls: &[Sysno],
+) -> PyResult<()> {
+    filter.default_action = Some(ScmpAction::KillProcess);
+    filter.landlock_paths = None;
+    let ctx = filter
+        .compile(false)
+        .map_err(|err| ExtraSafeError::new_err(format!("could not compile filter: {err}")))?;
+    let bpf = export_bpf(&ctx)
+        .map_err(|err| ExtraSafeError::new_err(format!("could not export filter: {err}")))?;
+    let insns = sock_filters(&bpf);
+
+    let mut allowed = Vec::new();
+    for &sysno in syscalls {
+        match py.allow_threads(|| probe(&insns, sysno)) {
+            Ok(true) => {},
+            Ok(false) => allowed.push(sysno.name()),
+            Err(msg) => {
+                return Err(ApplyError::new_err(format!(
+                    "could not probe {sysno}: {msg}"
+                )))
+            },
+        }
+    }
+    if !allowed.is_empty() {
+        return Err(ApplyError::new_err(format!(
+            "the filter does not deny the syscalls {}",
+            allowed.join(", "),
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the child was killed for using `sysno`.
+fn probe(insns: &[libc::sock_filter], sysno: Sysno) -> Result<bool, String> {
+    let program = libc::sock_fprog {
+        len: insns.len() as libc::c_ushort,
+        filter: insns.as_ptr().cast_mut(),
+    };
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(format!("fork failed: {}", std::io::Error::last_os_error()));
+    } else if pid == 0 {
+        // Only async-signal-safe syscalls in the child of a possibly multi-threaded process.
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+                || libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    0,
+                    &program,
+                ) != 0
+            {
+                libc::_exit(EXIT_NOT_INSTALLED);
+            }
+            // Invalid arguments, so that an allowed syscall fails without side effects.
+            let arg: libc::c_long = -1;
+            let _: libc::c_long = libc::syscall(sysno.id().into(), arg, arg, arg, arg, arg, arg);
+            libc::_exit(0);
+        }
+    }
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    let mut status = 0;
+    loop {
+        match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+            0 if Instant::now() < deadline => sleep(Duration::from_millis(1)),
+            0 => {
+                // The syscall blocks, so it was allowed.
+                unsafe {
+                    let _: libc::c_int = libc::kill(pid, libc::SIGKILL);
+                    let _: libc::pid_t = libc::waitpid(pid, &mut status, 0);
+                }
+                return Ok(false);
+            },
+            result if result < 0 => {
+                return Err(format!(
+                    "waitpid failed: {}",
+                    std::io::Error::last_os_error()
+                ));
+            },
+            _ => break,
+        }
+    }
+
+    if libc::WIFSIGNALED(status) {
+        Ok(libc::WTERMSIG(status) == libc::SIGSYS)
+    } else if libc::WEXITSTATUS(status) == EXIT_NOT_INSTALLED {
+        Err("the child process could not install the filter".to_owned())
+    } else {
+        Ok(false)
+    }
+}
+
+/// Let the kernel check the BPF program by installing it in a child process.
+/// Returns the errno of the kernel, if it rejected the program.
+pub(crate) fn kernel_validate(bpf: &[u8]) -> io::Result<Option<libc::c_int>> {
+    let insns = sock_filters(bpf);
+    let program = libc::sock_fprog {
+        len: insns.len() as libc::c_ushort,
+        filter: insns.as_ptr().cast_mut(),
+    };
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(io::Error::last_os_error());
+    } else if pid == 0 {
+        unsafe {
+            let errno = match libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) == 0
+                && libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    0,
+                    &program,
+                ) == 0
+            {
+                true => 0,
+                false => *libc::__errno_location(),
+            };
+            libc::_exit(errno);
+        }
+    }
+
+    let mut status = 0;
+    if unsafe { libc::waitpid(pid, &mut status, 0) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    match libc::WIFEXITED(status) {
+        true if libc::WEXITSTATUS(status) == 0 => Ok(None),
+        true => Ok(Some(libc::WEXITSTATUS(status))),
+        false => Err(io::Error::other(format!(
+            "the child process was killed by signal {}",
+            libc::WTERMSIG(status),
+        ))),
+    }
+}
+
+/// Time `iterations` calls of `sysno` in a child process, before and after the BPF program is
+/// installed. Returns the nanoseconds per call without and with the filter.
+pub(crate) fn benchmark(bpf: &[u8], sysno: Sysno, iterations: u32) -> io::Result<(f64, f64)> {
+    let insns = sock_filters(bpf);
+    let program = libc::sock_fprog {
+        len: insns.len() as libc::c_ushort,
+        filter: insns.as_ptr().cast_mut(),
+    };
+    // The filter might not allow the child to write anywhere, so it reports through shared memory.
+    let len = size_of::<[u64; 2]>();
+    let shared = unsafe {
+        libc::mmap(
+            null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if shared == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    let results = shared.cast::<[u64; 2]>();
+
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        let err = io::Error::last_os_error();
+        let _: libc::c_int = unsafe { libc::munmap(shared, len) };
+        return Err(err);
+    } else if pid == 0 {
+        unsafe {
+            let baseline = time_syscall(sysno, iterations);
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+                || libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    0,
+                    &program,
+                ) != 0
+            {
+                libc::_exit(EXIT_NOT_INSTALLED);
+            }
+            let sandboxed = time_syscall(sysno, iterations);
+            results.write_volatile([baseline, sandboxed]);
+            libc::_exit(0);
+        }
+    }
+
+    let mut status = 0;
+    let waited = unsafe { libc::waitpid(pid, &mut status, 0) };
+    let [baseline, sandboxed] = unsafe { results.read_volatile() };
+    let _: libc::c_int = unsafe { libc::munmap(shared, len) };
+    if waited < 0 {
+        return Err(io::Error::last_os_error());
+    } else if libc::WIFSIGNALED(status) {
+        return Err(io::Error::other(format!(
+            "the child process was killed by signal {}",
+            libc::WTERMSIG(status),
+        )));
+    } else if libc::WEXITSTATUS(status) != 0 {
+        return Err(io::Error::other(
+            "the child process could not install the filter",
+        ));
+    }
+    let per_call = |nanos: u64| nanos as f64 / f64::from(iterations);
+    Ok((per_call(baseline), per_call(sandboxed)))
+}
+
+/// The nanoseconds that `iterations` calls of `sysno` take, without any arguments.
+fn time_syscall(sysno: Sysno, iterations: u32) -> u64 {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let _: libc::c_long = unsafe { libc::syscall(sysno.id().into(), 0, 0) };
+    }
+    start.elapsed().as_nanos() as u64
+}
@@ -22,11 +22,19 @@ from pyextrasafe._pyextrasafe import (
     __license__,
     __version__,
     ExtraSafeError,
+    ApplyError,
+    CONTINUE,
     RuleSet,
     SafetyContext,
     BasicCapabilities,
     ForkAndExec,
+    IoUring,
     Networking,
+    ProcessControl,
+    Credentials,
+    MountNamespace,
+    PidFd,
+    Scheduling,
     SystemIO,
     Threads,
     Time,
@@ -34,18 +42,38 @@ from pyextrasafe._pyextrasafe import (
     # CompareOp,
     # Custom,
     # Rule,
+    apply_all,
+    apply_compute_only,
+    apply_strict_mode,
+    catalog,
+    describe,
+    explain_scope,
+    install_sitecustomize,
     lock_pid_file,
+    parse_seccomp_log,
+    reexec_sandboxed,
     restrict_privileges,
+    run_isolated,
+    sandbox_thread_initializer,
+    warn_on_leaked_locks,
 )
 
 
 __all__ = [
     "ExtraSafeError",
+    "ApplyError",
+    "CONTINUE",
     "RuleSet",
     "SafetyContext",
     "BasicCapabilities",
     "ForkAndExec",
+    "IoUring",
     "Networking",
+    "ProcessControl",
+    "Credentials",
+    "MountNamespace",
+    "PidFd",
+    "Scheduling",
     "SystemIO",
     "Threads",
     "Time",
@@ -53,6 +81,18 @@ __all__ = [
     # "CompareOp",
     # "Custom",
     # "Rule",
+    "apply_all",
+    "apply_compute_only",
+    "apply_strict_mode",
+    "catalog",
+    "describe",
+    "explain_scope",
+    "install_sitecustomize",
     "lock_pid_file",
+    "parse_seccomp_log",
+    "reexec_sandboxed",
     "restrict_privileges",
+    "run_isolated",
+    "sandbox_thread_initializer",
+    "warn_on_leaked_locks",
 ]
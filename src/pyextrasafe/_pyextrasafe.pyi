@@ -1,38 +1,175 @@
 from os import PathLike
-from typing import BinaryIO, Iterable, Literal, NewType, Optional, Union
+from typing import (
+    Any,
+    BinaryIO,
+    Callable,
+    Generic,
+    Iterable,
+    Literal,
+    NewType,
+    NoReturn,
+    Optional,
+    TypeVar,
+    Union,
+)
 
 Rule = NewType("Rule", list[str])
 Sysno = NewType("Sysno", int)
+T = TypeVar("T")
 
 __author__: str
 __license__: str
 __version__: str
 
+class _Continue:
+    "The decision of a handler to let the kernel execute a notified syscall."
+
+CONTINUE: _Continue
+
 class ExtraSafeError(Exception):
     "An exception thrown by PyExtraSafe."
 
+class ApplyError(ExtraSafeError):
+    "A seccomp filter could not be applied."
+    errno: Optional[int]
+    flags: Optional[int]
+    bpf_len: Optional[int]
+
 class SafetyContext:
     "A struct representing a set of rules to be loaded into a seccomp filter and applied to the current thread, or all threads in the current process."
 
-    def __init__(self) -> None:
+    def __init__(
+        self,
+        *,
+        name: Optional[str] = None,
+        strict_duplicates: bool = False,
+        on_violation: Literal["errno", "kill_thread", "kill_process"] = "errno",
+        include_basics: bool = True,
+    ) -> None:
         "Create a new SafetyContext. The seccomp filters will not be loaded until either apply_to_current_thread() or apply_to_all_threads() is called."
-    def apply_to_all_threads(self) -> None:
-        "Load the SafetyContext’s rules into a seccomp filter and apply the filter to all threads in this process."
-    def apply_to_current_thread(self) -> None:
+    @property
+    def name(self) -> Optional[str]:
+        "The name of the context, if it was given to the constructor."
+    def deduplicate(self) -> SafetyContext:
+        "Merge all enabled rule sets of the same type into one."
+    def allow_file_read(self, fileno: int) -> SafetyContext:
+        "Allow reading from a given open file descriptor in all SystemIO rule sets of this context."
+    def allow_file_write(self, fileno: int) -> SafetyContext:
+        "Allow writing to a given open file descriptor in all SystemIO rule sets of this context."
+    def forbid(self, names: list[str]) -> SafetyContext:
+        "Kill the process if it uses any of the given syscalls, even if a rule set allows them."
+    def notify(self, names: list[str]) -> SafetyContext:
+        "Let the handler of apply_with_handler() decide about the given syscalls."
+    def freeze(self) -> SafetyContext:
+        "Make this context and its enabled rule sets immutable."
+    def apply_to_all_threads(
+        self, *, audit_tag: Optional[str] = None, assert_denied: Optional[list[str]] = None
+    ) -> None:
+        "Load the SafetyContext’s rules into a seccomp filter and apply the filter to all threads in this process, including the calling thread."
+    def apply_to_current_thread(
+        self, *, audit_tag: Optional[str] = None, assert_denied: Optional[list[str]] = None
+    ) -> None:
         "Load the SafetyContext’s rules into a seccomp filter and apply the filter to the current thread."
-    def enable(self, *policies: list[RuleSet]) -> SafetyContext:
+    def apply_report_only(self, *, all_threads: bool = False) -> None:
+        "Apply the filter, but only log the syscalls that the policies do not allow."
+    def apply_to_threads(self, tids: Iterable[int], *, timeout: float = 1.0) -> None:
+        "Load the SafetyContext’s rules into a seccomp filter and apply the filter to the given threads of this process."
+    def apply_with_handler(
+        self,
+        handler: Callable[[int, tuple[int, ...]], Union[int, _Continue]],
+        *,
+        timeout: float = 1.0,
+    ) -> None:
+        "Apply the filter to the current thread, and let a handler decide about the notified syscalls."
+    def enable(self, *policies: Union[RuleSet, str], **flags: bool) -> SafetyContext:
         "Enable the simple and conditional rules provided by the RuleSet."
+    @staticmethod
+    def from_syscall_numbers(
+        nums: Iterable[int],
+        default_action: Literal["errno", "kill_thread", "kill_process"] = "kill_process",
+    ) -> SafetyContext:
+        "Create a context that allows exactly the given syscall numbers of the current architecture."
+    @staticmethod
+    def from_strace(
+        summary: str,
+        default_action: Literal["errno", "kill_thread", "kill_process"] = "kill_process",
+    ) -> tuple[SafetyContext, list[str]]:
+        "Create a context that allows exactly the syscalls in the summary of strace -c."
+    @staticmethod
+    def from_env(var: str = "PYEXTRASAFE_POLICY", *, required: bool = False) -> SafetyContext:
+        "Create a context from a JSON policy in an environment variable."
+    @staticmethod
+    def from_json(spec: str) -> SafetyContext:
+        "Create a context from a JSON policy."
+    @staticmethod
+    def startup_safe() -> SafetyContext:
+        "A baseline context that lets the interpreter start a script, e.g. for install_sitecustomize()."
+    def rule_sets(self) -> list[RuleSet]:
+        "Copies of the enabled rule sets."
+    def summary(self) -> dict[str, dict[str, list[Any]]]:
+        "A summary of all enabled flags and file descriptors, merged by rule set type."
+    def effective_flags(self) -> dict[str, Union[list[str], list[int]]]:
+        "The union of the flags per rule set type, including implicit rule sets, and the file descriptors."
+    def kernel_validate(self) -> None:
+        "Let the kernel check the filter in a child process, without installing it in this process."
+    def benchmark_syscall_overhead(self, iterations: int = 100000) -> dict[str, Union[str, float]]:
+        "Measure how much the filter adds to the latency of each syscall, in a child process."
+    def is_subset_of(self, other: SafetyContext) -> bool:
+        "Check that everything this context allows is allowed by the other context, too."
+    def added_syscalls(self, previous: SafetyContext) -> list[str]:
+        "The syscalls that this context allows, but the previous context did not."
+    def removed_syscalls(self, previous: SafetyContext) -> list[str]:
+        "The syscalls that the previous context allowed, but this context does not."
+    def filter_digest(self) -> str:
+        "The hex SHA-256 digest of the BPF program, that this context would install."
+    def to_c_array(self, var_name: str = "filter") -> str:
+        "Render the compiled BPF program as the initializer of a C struct sock_filter array."
+    def last_applied_bpf(self) -> Optional[bytes]:
+        "The BPF program that was installed by the most recent apply_to_current_thread() or apply_to_all_threads() call."
 
 class RuleSet:
     "A RuleSet is a collection of seccomp rules that enable a functionality."
+    def flags(self) -> list[str]:
+        "The names of the methods that were used to enable flags in this RuleSet."
+    def flag_bits(self) -> int:
+        "The enabled flags of this RuleSet as a bit set."
+    @staticmethod
+    def from_flag_bits(type_name: str, bits: int) -> RuleSet:
+        "Create a RuleSet from the result of flag_bits()."
+    def freeze(self: T) -> T:
+        "Make this RuleSet immutable, so that methods return modified copies instead."
+    def __or__(self: T, other: T) -> T:
+        "Merge two rule sets of the same type."
+    def is_subset_of(self, other: RuleSet) -> bool:
+        "Check that every syscall this rule set allows is allowed by the other rule set, too."
+    def is_superset_of(self, other: RuleSet) -> bool:
+        "Check that every syscall the other rule set allows is allowed by this rule set, too."
+    @property
+    def name(self) -> str:
+        "The name of this RuleSet, as used in error messages."
+    def syscalls(self) -> list[str]:
+        "The names of the syscalls that this RuleSet allows."
 
 class BasicCapabilities(RuleSet):
-    "Allow basic required syscalls to do things like allocate memory, and also a few that are used by Rust to set up panic handling and segfault handlers."
+    "Allow basic required syscalls to do things like allocate memory, exit, and also a few that are used by Rust to set up panic handling and segfault handlers. Always enabled in an applied SafetyContext."
     def __init__(self) -> None: ...
 
 class ForkAndExec(RuleSet):
     "Start another process, including more privileged ones. That process will still be under seccomp’s restrictions but depending on your filter it could still do bad things."
     def __init__(self) -> None: ...
+    def allow_wait_pid(self, pid: int) -> ForkAndExec:
+        "Only reap the given children."
+
+class IoUring(RuleSet):
+    "Allows io_uring syscalls. The operations submitted to an io_uring are not checked by seccomp filters."
+    def __init__(self) -> None:
+        "A new IoUring ruleset allows nothing by default."
+    def allow_setup(self) -> IoUring:
+        "Allow creating new io_uring instances."
+    def allow_enter(self) -> IoUring:
+        "Allow submitting operations to and waiting for completions of an io_uring."
+    def allow_register(self) -> IoUring:
+        "Allow registering files and buffers with an io_uring."
 
 class Networking(RuleSet):
     "Allows clone and sleep syscalls, which allow creating new threads and processes, and pausing them."
@@ -56,6 +193,104 @@ class Networking(RuleSet):
         "Allow starting new UDP sockets."
     def allow_start_unix_server(self) -> Networking:
         "Allow starting new Unix domain servers"
+    def allow_socketpair(self) -> Networking:
+        "Allow creating pairs of connected Unix sockets."
+    def allow_getsockopt(self) -> Networking:
+        "Allow reading socket options."
+    def allow_setsockopt(self) -> Networking:
+        "Allow setting socket options."
+    def allow_batched_udp(self) -> Networking:
+        "Allow sending and receiving multiple datagrams with one syscall."
+    def allow_socket(self) -> Networking:
+        "Allow creating sockets of any domain and type."
+    def allow_bind(self) -> Networking:
+        "Allow binding sockets to addresses."
+    def allow_listen(self) -> Networking:
+        "Allow listening for connections."
+    def allow_accept(self) -> Networking:
+        "Allow accepting connections."
+    def allow_connect(self) -> Networking:
+        "Allow connecting sockets."
+    def allow_shutdown(self) -> Networking:
+        "Allow shutting down one or both directions of a connection."
+    def allow_getsockname(self) -> Networking:
+        "Allow querying the local address of a socket."
+    def allow_getpeername(self) -> Networking:
+        "Allow querying the remote address of a connected socket."
+    def allow_fd_passing(self) -> _YesReally[Networking]:
+        "Allow recvmsg and sendmsg, which can pass file descriptors, after a confirmation."
+
+class ProcessControl(RuleSet):
+    "Allows syscalls that query and change harmless properties of the current process."
+    def __init__(self) -> None:
+        "A new ProcessControl ruleset allows nothing by default."
+    def allow_umask(self) -> ProcessControl:
+        "Allow setting the file mode creation mask."
+    def allow_getrusage(self) -> ProcessControl:
+        "Allow querying the resource usage of the process."
+    def allow_abort(self) -> ProcessControl:
+        "Allow the process to abort itself with SIGABRT."
+    def allow_process_vm_self(self) -> ProcessControl:
+        "Allow the process to read its own memory with process_vm_readv."
+    def allow_personality_query(self) -> ProcessControl:
+        "Allow reading the persona of the process, but not changing it."
+    def allow_prctl_option(self, option: int) -> ProcessControl:
+        "Allow the prctl syscall if its first argument is the given option."
+    def allow_madvise_advice(self, advice: int) -> ProcessControl:
+        "Allow the madvise syscall if its advice is the given value."
+    def allow_getrandom_flags(self, flags: int) -> ProcessControl:
+        "Allow the getrandom syscall if its flags are exactly the given flags."
+
+class Credentials(RuleSet):
+    "Allows syscalls that query and change the supplementary groups of the process."
+    def __init__(self) -> None:
+        "A new Credentials ruleset allows nothing by default."
+    def allow_getgroups(self) -> Credentials:
+        "Allow querying the supplementary groups of the process."
+    def allow_setgroups(self) -> Credentials:
+        "Allow changing the supplementary groups of the process."
+
+class _YesReally(Generic[T]):
+    "A dangerous flag that is only set after yes_really() was called."
+    def yes_really(self) -> T:
+        "Set the flag, and return the rule set."
+
+class MountNamespace(RuleSet):
+    "Allows syscalls that change the mounts of the process."
+    def __init__(self) -> None:
+        "A new MountNamespace ruleset allows nothing by default."
+    def allow_mount(self) -> _YesReally[MountNamespace]:
+        "Allow mounting filesystems, after a confirmation."
+    def allow_umount(self) -> _YesReally[MountNamespace]:
+        "Allow unmounting filesystems, after a confirmation."
+    def allow_pivot_root(self) -> _YesReally[MountNamespace]:
+        "Allow changing the root mount, after a confirmation."
+
+class PidFd(RuleSet):
+    "Allows syscalls that refer to processes by file descriptors instead of process IDs."
+    def __init__(self) -> None:
+        "A new PidFd ruleset allows nothing by default."
+    def allow_pidfd_open(self) -> PidFd:
+        "Allow getting a file descriptor for a process (Linux 5.3+)."
+    def allow_pidfd_send_signal(self) -> PidFd:
+        "Allow sending a signal to a process through its file descriptor (Linux 5.1+)."
+    def allow_pidfd_getfd(self) -> PidFd:
+        "Allow duplicating a file descriptor of another process (Linux 5.6+)."
+
+class Scheduling(RuleSet):
+    "Allows syscalls that query and change the scheduling of processes and threads."
+    def __init__(self) -> None:
+        "A new Scheduling ruleset allows nothing by default."
+    def allow_getpriority(self) -> Scheduling:
+        "Allow querying the nice value of a process."
+    def allow_setpriority(self) -> Scheduling:
+        "Allow changing the nice value of a process."
+    def allow_sched_getscheduler(self) -> Scheduling:
+        "Allow querying the scheduling policy of a process."
+    def allow_sched_setscheduler(self) -> Scheduling:
+        "Allow changing the scheduling policy of a process. Real-time policies need CAP_SYS_NICE."
+    def allow_getcpu(self) -> Scheduling:
+        "Allow querying the CPU and NUMA node that the current thread is running on."
 
 class SystemIO(RuleSet):
     "A RuleSet representing syscalls that perform IO - open/close/read/write/seek/stat."
@@ -85,10 +320,62 @@ class SystemIO(RuleSet):
         "Allow writing to stdout"
     def allow_write(self) -> SystemIO:
         "Allow write syscalls."
+    def allow_pipe(self) -> SystemIO:
+        "Allow creating anonymous pipes."
+    def allow_statfs(self) -> SystemIO:
+        "Allow statfs syscalls."
+    def allow_openat2(self) -> SystemIO:
+        "Allow the openat2 syscall, but not open or openat."
+    def allow_file_locking(self) -> SystemIO:
+        "Allow flock and the lock commands of fcntl."
+    def allow_truncate(self) -> SystemIO:
+        "Allow truncate and ftruncate syscalls."
+    def allow_fallocate(self) -> SystemIO:
+        "Allow the fallocate syscall."
+    def allow_fadvise(self) -> SystemIO:
+        "Allow the advisory fadvise64 syscall."
+    def allow_sync_file_range(self) -> SystemIO:
+        "Allow the advisory sync_file_range syscall."
+    def allow_rename(self) -> SystemIO:
+        "Allow renaming files with any flags."
+    def allow_scandir(self) -> SystemIO:
+        "Allow iterating over open directories, including the metadata of their entries."
+    def allow_low_fds(self, limit: int) -> SystemIO:
+        "Allow reading and writing all file descriptors below the limit."
+    def allow_fcntl_cmd(self, cmd: int) -> SystemIO:
+        "Allow the fcntl syscall if its command is exactly the given command."
+    def allow_open_flags(self, required_clear: int = 0, required_set: int = 0) -> SystemIO:
+        "Allow the open and openat syscalls if the flags in required_clear are clear, and the flags in required_set are set."
+    def allow_mkfifo(self) -> SystemIO:
+        "Allow creating named pipes and regular files with mknod, but not device nodes."
+    def allow_inotify(self) -> SystemIO:
+        "Allow watching the filesystem for changes with inotify."
+    def allow_dup(self) -> SystemIO:
+        "Allow duplicating file descriptors with the dup syscall."
+    def allow_dup2(self) -> SystemIO:
+        "Allow duplicating a file descriptor to a given number with the dup2 and dup3 syscalls."
+    def allow_fchmod(self) -> SystemIO:
+        "Allow changing the mode of an open file with fchmod."
+    def allow_fchown(self) -> SystemIO:
+        "Allow changing the owner and group of an open file with fchown."
+    def allow_memfd(self) -> SystemIO:
+        "Allow creating anonymous in-memory files, which could be executable."
+    def allow_memfd_noexec(self) -> SystemIO:
+        "Allow creating anonymous in-memory files, but only with MFD_NOEXEC_SEAL."
+    def allow_poll(self) -> SystemIO:
+        "Allow poll, select and epoll syscalls, including their signal-safe variants."
     def allow_file_read(self, fileno: int) -> SystemIO:
         "Allow reading a given open File. Note that with just this function, you will not be able to close the file under this context."
     def allow_file_write(self, fileno: int) -> SystemIO:
         "Allow writing to a given open File. Note that with just this function, you will not be able to close the file under this context."
+    def is_file_read_allowed(self, fileno: int) -> bool:
+        "Whether the file descriptor was registered with allow_file_read()."
+    def is_file_write_allowed(self, fileno: int) -> bool:
+        "Whether the file descriptor was registered with allow_file_write()."
+    def allow_open_within(self, paths: Iterable[Union[str, PathLike]]) -> SystemIO:
+        "Allow open syscalls, but use landlock to restrict all filesystem access to the given paths."
+    def allow_rename_flags(self, flags: int) -> SystemIO:
+        "Allow the renameat2 syscall if its flags argument is exactly the given flags."
 
 class Threads(RuleSet):
     "Allows clone and sleep syscalls, which allow creating new threads and processes, and pausing them."
@@ -98,6 +385,8 @@ class Threads(RuleSet):
         "Allow creating new threads and processes."
     def allow_sleep(self) -> Threads:
         "Allow sleeping on the current thread"
+    def allow_set_tid_address(self) -> Threads:
+        "Allow the syscall set_tid_address, without allowing to create threads."
 
 class Time(RuleSet):
     "Enable syscalls related to time."
@@ -105,6 +394,48 @@ class Time(RuleSet):
         "A new Time RuleSet allows nothing by default."
     def allow_gettime(self) -> Time:
         "On most 64 bit systems glibc and musl both use the vDSO to compute the time directly with rdtsc rather than calling the clock_gettime syscall, so in most cases you don’t need to actually enable this."
+    def allow_adjtime(self) -> _YesReally[Time]:
+        "Allow adjtimex and clock_adjtime, which can read and adjust the system clock."
+    def allow_timerfd(self) -> Time:
+        "Allow timers that notify through a file descriptor, for all flags."
+    def allow_timerfd_relative(self) -> Time:
+        "Allow timers that notify through a file descriptor, but only relative ones without TFD_TIMER_ABSTIME."
+
+def apply_strict_mode() -> None:
+    "Put the current thread into seccomp strict mode, which only allows read, write, exit and sigreturn."
+
+def apply_all(
+    contexts: list[SafetyContext],
+    target: Literal["current_thread", "all_threads"] = "current_thread",
+) -> None:
+    "Apply multiple contexts in order, but compile all of them first."
+
+def apply_compute_only() -> None:
+    "Sandbox the current thread, so that it can only compute, and read and write open file descriptors."
+
+def catalog() -> dict[str, list[str]]:
+    "All built-in RuleSet class names, mapped to the names of their flag methods."
+
+def describe(context: SafetyContext) -> str:
+    "Render the policy of a context as a human readable tree."
+
+def explain_scope() -> str:
+    "A short explanation which code is governed by a seccomp filter."
+
+def install_sitecustomize(context: SafetyContext, directory: Union[str, PathLike]) -> str:
+    "Write a sitecustomize module into the directory, that applies the context when the interpreter starts."
+
+def run_isolated(context: SafetyContext, func: Callable[..., T], *args: Any) -> T:
+    "Run func(*args) in a sandboxed child process, and return its result."
+
+def sandbox_thread_initializer(context: SafetyContext) -> Callable[[], None]:
+    "Create an initializer that applies the context to each worker thread of a thread pool."
+
+def parse_seccomp_log(lines: Iterable[str]) -> set[str]:
+    "Collect the names of the syscalls in the seccomp entries of the kernel audit log."
+
+def reexec_sandboxed(context: SafetyContext, argv: list[str]) -> NoReturn:
+    "Apply the context to the current thread, and replace the process with the program argv."
 
 def restrict_privileges() -> None:
     "Basic security setup to prevent bootstrapping attacks."
@@ -116,9 +447,13 @@ def lock_pid_file(
     cloexec: bool = True,
     mode: int = 0o640,
     contents: Optional[bytes] = None,
+    metadata: Optional[dict[str, object]] = None,
 ) -> BinaryIO:
     "Open and file-lock a PID file to prevent running multiple instances of a program."
 
+def warn_on_leaked_locks(enabled: bool) -> None:
+    "Emit a ResourceWarning if a file returned by lock_pid_file() is collected without closefd=True."
+
 # class CompareOp:
 #     "Represents a comparison operator which can be used in a filter rule."
 #     NotEqual: CompareOp
@@ -0,0 +1,239 @@
+//! Install a seccomp filter on other threads of the current process.
+//!
+//! A thread can only install a filter on itself, or synchronize its own filters to all threads of
+//! the process. To sandbox a selection of threads, each of them is interrupted by a signal, and
+//! the signal handler installs the filter.
+//!
+//! Each signal carries the generation of its request. A signal that arrives after its request
+//! timed out does nothing. The previous signal handler is restored once every signal of a request
+//! was handled. If a thread timed out, then its signal could still be pending, and the default
+//! action of real-time signals is to terminate the process, so our handler stays installed.
+
+use std::io;
+use std::mem::{forget, zeroed};
+use std::ptr::{addr_of_mut, null_mut};
+use std::sync::atomic::{AtomicI32, AtomicI64, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::filter::sock_filters;
+
+/// The program the signal handler installs, or null if there is no active request.
+static PROGRAM: AtomicPtr<libc::sock_fprog> = AtomicPtr::new(null_mut());
+/// The thread that should install [`PROGRAM`].
+static TARGET: AtomicI32 = AtomicI32::new(0);
+/// The answering thread in the upper half, and its errno or `0` in the lower half.
+/// A thread that timed out might answer late, so the answer is only valid if the thread matches.
+static RESULT: AtomicI64 = AtomicI64::new(0);
+/// The generation of the active request, which each signal carries.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+/// Only one request can be active at a time.
+static REQUEST: Mutex<Handler> = Mutex::new(Handler {
+    previous: None,
+    keep: false,
+});
+
+/// The state of our signal handler.
+struct Handler {
+    /// The handler that our handler replaced, while ours is installed.
+    previous: Option<libc::sigaction>,
+    /// A thread timed out, so our handler must never be uninstalled.
+    keep: bool,
+}
+
+/// The start of `siginfo_t` for a signal sent with `SI_QUEUE`, which the `libc` crate does not
+/// expose. The fields after `code` are in a union that is aligned like a pointer.
+#[repr(C)]
+struct QueuedInfo {
+    signo: libc::c_int,
+    errno: libc::c_int,
+    code: libc::c_int,
+    queued: Queued,
+}
+
+#[repr(C)]
+struct Queued {
+    pid: libc::pid_t,
+    uid: libc::uid_t,
+    value: usize,
+}
+
+/// The signal that is used to interrupt the threads.
+fn signal() -> libc::c_int {
+    libc::SIGRTMAX()
+}
+
+/// Why a thread could not be sandboxed.
+#[derive(Debug)]
+pub(crate) enum ThreadError {
+    Os(io::Error),
+    TimedOut,
+}
+
+/// Install the BPF program on the threads `tids`. Returns the threads that could not be sandboxed.
+pub(crate) fn install_on_threads(
+    bpf: &[u8],
+    tids: &[libc::pid_t],
+    timeout: Duration,
+) -> io::Result<Vec<(libc::pid_t, ThreadError)>> {
+    let mut handler = REQUEST.lock().unwrap_or_else(|err| err.into_inner());
+    handler.install()?;
+
+    let mut filter = sock_filters(bpf);
+    let program = Box::into_raw(Box::new(libc::sock_fprog {
+        len: filter.len() as libc::c_ushort,
+        filter: filter.as_mut_ptr(),
+    }));
+
+    // Sandboxing the current thread could forbid interrupting the other threads.
+    let own_tid = unsafe { libc::gettid() };
+    let (own, others): (Vec<_>, Vec<_>) = tids.iter().partition(|&&tid| tid == own_tid);
+
+    let pid = unsafe { libc::getpid() };
+    let mut errors = Vec::new();
+    let mut timed_out = false;
+    for tid in others.into_iter().chain(own.into_iter().take(1)) {
+        let generation = GENERATION.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
+        RESULT.store(0, Ordering::SeqCst);
+        TARGET.store(tid, Ordering::SeqCst);
+        PROGRAM.store(program, Ordering::SeqCst);
+
+        let result = match send_signal(pid, tid, generation) {
+            Ok(()) => wait_for_result(tid, timeout),
+            Err(err) => Err(ThreadError::Os(err)),
+        };
+
+        // A signal that is handled from now on belongs to an old generation.
+        let _: usize = GENERATION.fetch_add(1, Ordering::SeqCst);
+        PROGRAM.store(null_mut(), Ordering::SeqCst);
+        TARGET.store(0, Ordering::SeqCst);
+        if let Err(err) = result {
+            timed_out |= matches!(err, ThreadError::TimedOut);
+            errors.push((tid, err));
+        }
+    }
+
+    // A thread that did not answer in time might still use the program.
+    if !timed_out {
+        drop(unsafe { Box::from_raw(program) });
+        drop(filter);
+        handler.restore()?;
+    } else {
+        forget(filter);
+        handler.keep = true;
+    }
+    Ok(errors)
+}
+
+/// Send [`signal()`] to the thread `tid`, with the generation as its value.
+fn send_signal(pid: libc::pid_t, tid: libc::pid_t, generation: usize) -> io::Result<()> {
+    let mut info: libc::siginfo_t = unsafe { zeroed() };
+    let queued = unsafe { &mut *addr_of_mut!(info).cast::<QueuedInfo>() };
+    queued.signo = signal();
+    queued.code = libc::SI_QUEUE;
+    queued.queued = Queued {
+        pid,
+        uid: unsafe { libc::getuid() },
+        value: generation,
+    };
+    let sent = unsafe {
+        libc::syscall(
+            libc::SYS_rt_tgsigqueueinfo,
+            pid,
+            tid,
+            signal(),
+            addr_of_mut!(info),
+        )
+    };
+    match sent {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn wait_for_result(tid: libc::pid_t, timeout: Duration) -> Result<(), ThreadError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let result = RESULT.load(Ordering::SeqCst);
+        if (result >> 32) as libc::pid_t == tid {
+            return match result as libc::c_int {
+                0 => Ok(()),
+                errno => Err(ThreadError::Os(io::Error::from_raw_os_error(errno))),
+            };
+        } else if Instant::now() >= deadline {
+            return Err(ThreadError::TimedOut);
+        }
+        sleep(Duration::from_millis(1));
+    }
+}
+
+impl Handler {
+    /// Install our handler, unless it is installed already.
+    fn install(&mut self) -> io::Result<()> {
+        if self.previous.is_some() {
+            return Ok(());
+        }
+        unsafe {
+            let mut action: libc::sigaction = zeroed();
+            let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+                handler;
+            action.sa_sigaction = handler as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART | libc::SA_SIGINFO;
+            let _: libc::c_int = libc::sigemptyset(addr_of_mut!(action.sa_mask));
+            let mut previous: libc::sigaction = zeroed();
+            match libc::sigaction(signal(), &action, &mut previous) {
+                0 => {
+                    self.previous = Some(previous);
+                    Ok(())
+                },
+                _ => Err(io::Error::last_os_error()),
+            }
+        }
+    }
+
+    /// Restore the handler that was installed before ours, unless a thread ever timed out.
+    fn restore(&mut self) -> io::Result<()> {
+        let Some(previous) = self.previous.filter(|_| !self.keep) else {
+            return Ok(());
+        };
+        match unsafe { libc::sigaction(signal(), &previous, null_mut()) } {
+            0 => {
+                self.previous = None;
+                Ok(())
+            },
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+extern "C" fn handler(_: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    unsafe {
+        let saved_errno = *libc::__errno_location();
+        let tid = libc::gettid();
+        let info = &*info.cast::<QueuedInfo>();
+        if info.code == libc::SI_QUEUE
+            && info.queued.value == GENERATION.load(Ordering::SeqCst)
+            && TARGET.load(Ordering::SeqCst) == tid
+        {
+            let program = PROGRAM.load(Ordering::SeqCst);
+            if !program.is_null() {
+                let result = match libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) {
+                    0 => {
+                        libc::syscall(libc::SYS_seccomp, libc::SECCOMP_SET_MODE_FILTER, 0, program)
+                    },
+                    err => err.into(),
+                };
+                let errno = match result {
+                    0 => 0,
+                    _ => *libc::__errno_location(),
+                };
+                RESULT.store(
+                    i64::from(tid) << 32 | i64::from(errno as u32),
+                    Ordering::SeqCst,
+                );
+            }
+        }
+        *libc::__errno_location() = saved_errno;
+    }
+}
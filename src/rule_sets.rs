@@ -1,81 +1,182 @@
-use std::fmt::{self, Write};
+use std::collections::HashMap;
 use std::fs::File;
 use std::hash::Hash;
 use std::mem::ManuallyDrop;
 use std::os::fd::{FromRawFd, RawFd};
+use std::path::PathBuf;
 
 use bitflags::bitflags;
 use extrasafe::builtins::danger_zone::{ForkAndExec, Threads};
 use extrasafe::builtins::network::Networking;
 use extrasafe::builtins::{BasicCapabilities, SystemIO, Time};
-use extrasafe::SafetyContext;
+use extrasafe::{Rule, RuleSet};
+use libseccomp::{ScmpArgCompare, ScmpCompareOp};
+use pyo3::pyclass::boolean_struct::False;
+use pyo3::types::{PyDict, PyList};
 use pyo3::{
-    pyclass, pymethods, Py, PyAny, PyClassInitializer, PyRef, PyRefMut, PyResult, Python,
-    ToPyObject,
+    pyclass, pyfunction, pymethods, IntoPy, Py, PyAny, PyCell, PyClass, PyClassInitializer, PyRef,
+    PyRefMut, PyResult, Python, ToPyObject,
 };
+use rustix::process::getpid;
+use syscalls::Sysno;
 
 // use crate::custom::DataCustom;
+use crate::filter::Filter;
 use crate::ExtraSafeError;
 
-trait EnableExtra<P> {
-    fn enable_extra(&self, policy: P) -> P;
+trait EnableExtra<P, F> {
+    fn enable_extra(
+        &self,
+        policy: P,
+        flags: &F,
+        filter: &mut Filter,
+    ) -> Result<P, extrasafe::ExtraSafeError>;
 }
 
-impl<P> EnableExtra<P> for () {
+impl<P, F> EnableExtra<P, F> for () {
     #[inline]
-    fn enable_extra(&self, policy: P) -> P {
-        policy
+    fn enable_extra(
+        &self,
+        policy: P,
+        _: &F,
+        _: &mut Filter,
+    ) -> Result<P, extrasafe::ExtraSafeError> {
+        Ok(policy)
     }
 }
 
-struct ReprExtra<'a, D>(&'a D);
+trait SummarizeExtra {
+    fn summarize_to(&self, summary: &PyDict) -> PyResult<()>;
+}
+
+impl SummarizeExtra for () {
+    #[inline]
+    fn summarize_to(&self, _: &PyDict) -> PyResult<()> {
+        Ok(())
+    }
+}
+
+trait MergeExtra {
+    fn merge_extra(&mut self, other: &Self);
+}
 
-const _: () = {
-    impl<D: DebugExtra> fmt::Display for ReprExtra<'_, D> {
-        fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.0.format_to(formatter)
+impl MergeExtra for () {
+    #[inline]
+    fn merge_extra(&mut self, _: &Self) {}
+}
+
+/// Insert the `items` into the sorted list `into`, unless they are already contained.
+fn merge_sorted<T: Ord + Copy>(into: &mut Vec<T>, items: &[T]) {
+    for &item in items {
+        if let Err(pos) = into.binary_search(&item) {
+            into.insert(pos, item);
         }
     }
+}
 
-    trait DebugExtra {
-        fn format_to(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result;
+/// Append the `items` to the list `summary[key]`, unless they are already contained.
+fn merge_summary<T: ToPyObject>(
+    summary: &PyDict,
+    key: &str,
+    items: impl IntoIterator<Item = T>,
+) -> PyResult<()> {
+    let list = match summary.get_item(key) {
+        Some(list) => list.downcast::<PyList>()?,
+        None => {
+            let list = PyList::empty(summary.py());
+            summary.set_item(key, list)?;
+            list
+        },
+    };
+    for item in items {
+        let item = item.to_object(summary.py());
+        if !list.contains(&item)? {
+            list.append(item)?;
+        }
     }
+    Ok(())
+}
 
-    impl DebugExtra for () {
-        #[inline]
-        fn format_to(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
-            Ok(())
+/// A representation like `SystemIO(flags=['allow_read'], read_fds=[3])`.
+fn repr_policy(py: Python<'_>, policy: &dyn InspectPolicy) -> PyResult<String> {
+    let summary = PyDict::new(py);
+    policy.summarize_to(summary)?;
+
+    let mut s = format!("{}(", policy.name());
+    let mut first = true;
+    for (key, value) in summary {
+        if key.str()?.to_str()? != "flags" && value.len()? == 0 {
+            continue;
+        }
+        if !first {
+            s.push_str(", ");
         }
+        first = false;
+        s.push_str(key.str()?.to_str()?);
+        s.push('=');
+        s.push_str(value.repr()?.to_str()?);
     }
+    s.push(')');
+    Ok(s)
+}
 
-    impl DebugExtra for ReadWriteFilenos {
-        fn format_to(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-            let rd = self.rd.as_slice();
-            let wr = self.wr.as_slice();
-            if rd.is_empty() && wr.is_empty() {
-                return Ok(());
-            }
+/// Syscalls that are not covered by the wrapped extrasafe rule set.
+#[derive(Debug)]
+pub(crate) struct ExtraRules {
+    name: &'static str,
+    pub(crate) simple: Vec<Sysno>,
+    conditional: HashMap<Sysno, Vec<Rule>>,
+}
 
-            formatter.write_str(", ")?;
-            formatter
-                .debug_map()
-                .entry(&"rd", &rd)
-                .entry(&"wr", &wr)
-                .finish()
+impl ExtraRules {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            simple: Vec::new(),
+            conditional: HashMap::new(),
         }
     }
-};
+
+    /// Allow `sysno` if its argument `arg` equals any of the `values`.
+    fn allow_arg_values(&mut self, sysno: Sysno, arg: u32, values: &[u64]) {
+        let rules = self.conditional.entry(sysno).or_default();
+        for &value in values {
+            let comparator = ScmpArgCompare::new(arg, ScmpCompareOp::Equal, value);
+            rules.push(Rule::new(sysno).and_condition(comparator));
+        }
+    }
+}
+
+impl RuleSet for ExtraRules {
+    fn simple_rules(&self) -> Vec<Sysno> {
+        self.simple.clone()
+    }
+
+    fn conditional_rules(&self) -> HashMap<Sysno, Vec<Rule>> {
+        self.conditional.clone()
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
 
 pub(crate) trait EnablePolicy {
-    fn enable_to(&self, ctx: SafetyContext) -> Result<SafetyContext, extrasafe::ExtraSafeError>;
+    fn enable_to(&self, filter: &mut Filter) -> Result<(), extrasafe::ExtraSafeError>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) enum DataRuleSet {
     PyBasicCapabilities(DataBasicCapabilities),
     PyForkAndExec(DataForkAndExec),
     PyThreads(DataThreads),
+    PyIoUring(DataIoUring),
     PyNetworking(DataNetworking),
+    PyProcessControl(DataProcessControl),
+    PyCredentials(DataCredentials),
+    PyMountNamespace(DataMountNamespace),
+    PyPidFd(DataPidFd),
+    PyScheduling(DataScheduling),
     PySystemIO(Box<DataSystemIO>),
     PyTime(DataTime),
     // PyCustom(Box<DataCustom>),
@@ -83,32 +184,370 @@ pub(crate) enum DataRuleSet {
 
 impl EnablePolicy for PyRuleSet {
     #[inline]
-    fn enable_to(&self, ctx: SafetyContext) -> Result<SafetyContext, extrasafe::ExtraSafeError> {
-        self.0.enable_to(ctx)
+    fn enable_to(&self, filter: &mut Filter) -> Result<(), extrasafe::ExtraSafeError> {
+        self.data.enable_to(filter)
     }
 }
 
 impl EnablePolicy for DataRuleSet {
-    fn enable_to(&self, ctx: SafetyContext) -> Result<SafetyContext, extrasafe::ExtraSafeError> {
+    fn enable_to(&self, filter: &mut Filter) -> Result<(), extrasafe::ExtraSafeError> {
         match self {
-            DataRuleSet::PyBasicCapabilities(policy) => policy.enable_to(ctx),
-            DataRuleSet::PyForkAndExec(policy) => policy.enable_to(ctx),
-            DataRuleSet::PyThreads(policy) => policy.enable_to(ctx),
-            DataRuleSet::PyNetworking(policy) => policy.enable_to(ctx),
-            DataRuleSet::PySystemIO(policy) => policy.enable_to(ctx),
-            DataRuleSet::PyTime(policy) => policy.enable_to(ctx),
-            // DataRuleSet::PyCustom(policy) => policy.enable_to(ctx),
+            DataRuleSet::PyBasicCapabilities(policy) => policy.enable_to(filter),
+            DataRuleSet::PyForkAndExec(policy) => policy.enable_to(filter),
+            DataRuleSet::PyThreads(policy) => policy.enable_to(filter),
+            DataRuleSet::PyIoUring(policy) => policy.enable_to(filter),
+            DataRuleSet::PyNetworking(policy) => policy.enable_to(filter),
+            DataRuleSet::PyProcessControl(policy) => policy.enable_to(filter),
+            DataRuleSet::PyCredentials(policy) => policy.enable_to(filter),
+            DataRuleSet::PyMountNamespace(policy) => policy.enable_to(filter),
+            DataRuleSet::PyPidFd(policy) => policy.enable_to(filter),
+            DataRuleSet::PyScheduling(policy) => policy.enable_to(filter),
+            DataRuleSet::PySystemIO(policy) => policy.enable_to(filter),
+            DataRuleSet::PyTime(policy) => policy.enable_to(filter),
+            // DataRuleSet::PyCustom(policy) => policy.enable_to(filter),
         }
     }
 }
 
+pub(crate) trait InspectPolicy {
+    fn name(&self) -> &'static str;
+    fn flag_names(&self) -> Vec<&'static str>;
+    fn flag_bits(&self) -> u32;
+    /// The names of the flags in `bits`, in the order of their declaration.
+    fn flag_names_of(&self, bits: u32) -> Vec<&'static str>;
+    fn summarize_to(&self, summary: &PyDict) -> PyResult<()>;
+}
+
+impl DataRuleSet {
+    pub(crate) fn inspect(&self) -> &dyn InspectPolicy {
+        match self {
+            DataRuleSet::PyBasicCapabilities(policy) => policy,
+            DataRuleSet::PyForkAndExec(policy) => policy,
+            DataRuleSet::PyThreads(policy) => policy,
+            DataRuleSet::PyIoUring(policy) => policy,
+            DataRuleSet::PyNetworking(policy) => policy,
+            DataRuleSet::PyProcessControl(policy) => policy,
+            DataRuleSet::PyCredentials(policy) => policy,
+            DataRuleSet::PyMountNamespace(policy) => policy,
+            DataRuleSet::PyPidFd(policy) => policy,
+            DataRuleSet::PyScheduling(policy) => policy,
+            DataRuleSet::PySystemIO(policy) => &**policy,
+            DataRuleSet::PyTime(policy) => policy,
+            // DataRuleSet::PyCustom(policy) => &**policy,
+        }
+    }
+
+    /// A new rule set of every type, that allow nothing.
+    fn all() -> [Self; 12] {
+        [
+            DataRuleSet::PyBasicCapabilities(Default::default()),
+            DataRuleSet::PyForkAndExec(Default::default()),
+            DataRuleSet::PyThreads(Default::default()),
+            DataRuleSet::PyIoUring(Default::default()),
+            DataRuleSet::PyNetworking(Default::default()),
+            DataRuleSet::PyProcessControl(Default::default()),
+            DataRuleSet::PyCredentials(Default::default()),
+            DataRuleSet::PyMountNamespace(Default::default()),
+            DataRuleSet::PyPidFd(Default::default()),
+            DataRuleSet::PyScheduling(Default::default()),
+            DataRuleSet::PySystemIO(Default::default()),
+            DataRuleSet::PyTime(Default::default()),
+        ]
+    }
+
+    /// A new rule set that allows nothing, by the lowercase name of its class.
+    fn named(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|data| data.inspect().name().to_lowercase() == name)
+    }
+
+    /// Add the flags and rules of `other` to this rule set. Returns `false` if the types differ.
+    fn merge_from(&mut self, other: &DataRuleSet) -> bool {
+        match (self, other) {
+            (DataRuleSet::PyBasicCapabilities(a), DataRuleSet::PyBasicCapabilities(b)) => {
+                a.merge_from(b);
+            },
+            (DataRuleSet::PyForkAndExec(a), DataRuleSet::PyForkAndExec(b)) => a.merge_from(b),
+            (DataRuleSet::PyThreads(a), DataRuleSet::PyThreads(b)) => a.merge_from(b),
+            (DataRuleSet::PyIoUring(a), DataRuleSet::PyIoUring(b)) => a.merge_from(b),
+            (DataRuleSet::PyNetworking(a), DataRuleSet::PyNetworking(b)) => a.merge_from(b),
+            (DataRuleSet::PyProcessControl(a), DataRuleSet::PyProcessControl(b)) => {
+                a.merge_from(b);
+            },
+            (DataRuleSet::PyCredentials(a), DataRuleSet::PyCredentials(b)) => a.merge_from(b),
+            (DataRuleSet::PyMountNamespace(a), DataRuleSet::PyMountNamespace(b)) => a.merge_from(b),
+            (DataRuleSet::PyPidFd(a), DataRuleSet::PyPidFd(b)) => a.merge_from(b),
+            (DataRuleSet::PyScheduling(a), DataRuleSet::PyScheduling(b)) => a.merge_from(b),
+            (DataRuleSet::PySystemIO(a), DataRuleSet::PySystemIO(b)) => a.merge_from(b),
+            (DataRuleSet::PyTime(a), DataRuleSet::PyTime(b)) => a.merge_from(b),
+            _ => return false,
+        }
+        true
+    }
+
+    /// A flag that is only set after a confirmation was given in the code, so it must not be set
+    /// from bits or JSON.
+    pub(crate) fn confirmed_flag(&self) -> Option<&'static str> {
+        match self {
+            DataRuleSet::PyMountNamespace(data) => data.flag_names().first().copied(),
+            DataRuleSet::PyNetworking(data)
+                if data.flags.contains(FlagsNetworking::ALLOW_FD_PASSING)
+                    && !data.flags.intersects(GROUPED) =>
+            {
+                Some("allow_fd_passing")
+            },
+            DataRuleSet::PyTime(data) if data.flags.contains(FlagsTime::ALLOW_ADJTIME) => {
+                Some("allow_adjtime")
+            },
+            _ => None,
+        }
+    }
+
+    /// The file descriptors that a [`PySystemIO`] allows to read and to write.
+    pub(crate) fn file_descriptors(&self) -> Option<(&[RawFd], &[RawFd])> {
+        match self {
+            DataRuleSet::PySystemIO(data) => Some((&data.extra.rd, &data.extra.wr)),
+            _ => None,
+        }
+    }
+
+    /// Replace the flags of the rule set. Returns `false` if `bits` contains unknown flags.
+    fn set_flag_bits(&mut self, bits: u32) -> bool {
+        match self {
+            DataRuleSet::PyBasicCapabilities(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyForkAndExec(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyThreads(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyIoUring(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyNetworking(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyProcessControl(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyCredentials(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyMountNamespace(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyPidFd(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyScheduling(data) => data.set_flag_bits(bits),
+            DataRuleSet::PySystemIO(data) => data.set_flag_bits(bits),
+            DataRuleSet::PyTime(data) => data.set_flag_bits(bits),
+        }
+    }
+
+    /// The [`BasicCapabilities`], that [`Filter::compile()`] enables implicitly.
+    pub(crate) fn basics() -> &'static dyn InspectPolicy {
+        const BASICS: DataBasicCapabilities = DataBasicCapabilities {
+            flags: FlagsBasicCapabilities::empty(),
+            extra: (),
+        };
+        &BASICS
+    }
+}
+
+#[pyfunction]
+pub(crate) fn catalog(py: Python<'_>) -> PyResult<Py<PyDict>> {
+    let result = PyDict::new(py);
+    for data in DataRuleSet::all() {
+        let policy = data.inspect();
+        result.set_item(policy.name(), policy.flag_names_of(u32::MAX))?;
+    }
+    Ok(result.into())
+}
+
 #[pyclass]
 #[pyo3(name = "RuleSet", module = "pyextrasafe", subclass)]
-#[derive(Debug)]
-pub(crate) struct PyRuleSet(pub(crate) DataRuleSet);
+#[derive(Debug, Clone)]
+pub(crate) struct PyRuleSet {
+    pub(crate) data: DataRuleSet,
+    pub(crate) frozen: bool,
+}
+
+impl PyRuleSet {
+    fn new(data: DataRuleSet) -> Self {
+        Self {
+            data,
+            frozen: false,
+        }
+    }
+
+    /// Wrap the rule set in a new instance of its concrete Python class.
+    pub(crate) fn into_instance(self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        match self.data {
+            DataRuleSet::PyBasicCapabilities(_) => PyBasicCapabilities::wrap(py, self),
+            DataRuleSet::PyForkAndExec(_) => PyForkAndExec::wrap(py, self),
+            DataRuleSet::PyThreads(_) => PyThreads::wrap(py, self),
+            DataRuleSet::PyIoUring(_) => PyIoUring::wrap(py, self),
+            DataRuleSet::PyNetworking(_) => PyNetworking::wrap(py, self),
+            DataRuleSet::PyProcessControl(_) => PyProcessControl::wrap(py, self),
+            DataRuleSet::PyCredentials(_) => PyCredentials::wrap(py, self),
+            DataRuleSet::PyMountNamespace(_) => PyMountNamespace::wrap(py, self),
+            DataRuleSet::PyPidFd(_) => PyPidFd::wrap(py, self),
+            DataRuleSet::PyScheduling(_) => PyScheduling::wrap(py, self),
+            DataRuleSet::PySystemIO(_) => PySystemIO::wrap(py, self),
+            DataRuleSet::PyTime(_) => PyTime::wrap(py, self),
+            // DataRuleSet::PyCustom(_) => PyCustom::wrap(py, self),
+        }
+    }
+
+    /// A new instance of the rule set class with the lowercase name `name`, that allows nothing.
+    pub(crate) fn new_named(py: Python<'_>, name: &str) -> PyResult<Option<Py<PyAny>>> {
+        DataRuleSet::named(name)
+            .map(|data| Self::new(data).into_instance(py))
+            .transpose()
+    }
+
+    /// A copy of the rule set as a new instance of its concrete Python class.
+    pub(crate) fn to_py_copy(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        self.clone().into_instance(py)
+    }
+
+    fn add_flag_bits(&mut self, bits: u32) {
+        let bits = self.data.inspect().flag_bits() | bits;
+        if !self.data.set_flag_bits(bits) {
+            unreachable!("Impossible content");
+        }
+    }
+
+    /// Modify the rule set, or a copy of the rule set if it is frozen.
+    fn update<T>(
+        mut this: PyRefMut<'_, T>,
+        modify: impl FnOnce(&mut DataRuleSet) -> PyResult<()>,
+    ) -> PyResult<Py<PyAny>>
+    where
+        T: PyClass<BaseType = PyRuleSet, Frozen = False>,
+    {
+        let py = this.py();
+        let base = this.as_mut();
+        if base.frozen {
+            let mut copy = base.clone();
+            modify(&mut copy.data)?;
+            copy.into_instance(py)
+        } else {
+            modify(&mut base.data)?;
+            Ok(this.into_py(py))
+        }
+    }
+
+    /// A new, unfrozen instance with the flags and rules of both rule sets, if they are of the same
+    /// type.
+    pub(crate) fn merged(&self, py: Python<'_>, other: &PyRuleSet) -> PyResult<Option<Py<PyAny>>> {
+        let mut data = self.data.clone();
+        match data.merge_from(&other.data) {
+            true => Self::new(data).into_instance(py).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    pub(crate) fn to_filter(&self) -> PyResult<Filter> {
+        let mut filter = Filter::default();
+        self.enable_to(&mut filter).map_err(|err| {
+            ExtraSafeError::new_err(format!("policy {self:?} could not be applied: {err}"))
+        })?;
+        Ok(filter)
+    }
+}
 
 #[pymethods]
-impl PyRuleSet {}
+impl PyRuleSet {
+    fn flags(&self) -> Vec<&'static str> {
+        self.data.inspect().flag_names()
+    }
+
+    fn flag_bits(&self) -> u32 {
+        self.data.inspect().flag_bits()
+    }
+
+    #[staticmethod]
+    fn from_flag_bits(py: Python<'_>, type_name: &str, bits: u32) -> PyResult<Py<PyAny>> {
+        let Some(mut data) = DataRuleSet::named(&type_name.to_lowercase()) else {
+            return Err(ExtraSafeError::new_err(format!(
+                "unknown rule set {type_name:?}"
+            )));
+        };
+        if !data.set_flag_bits(bits) {
+            return Err(ExtraSafeError::new_err(format!(
+                "unknown flag bits {bits:#x} of rule set {type_name:?}"
+            )));
+        } else if let Some(flag) = data.confirmed_flag() {
+            return Err(ExtraSafeError::new_err(format!(
+                "the flag {flag} of rule set {type_name:?} needs a confirmation, \
+                it cannot be set from bits"
+            )));
+        }
+        Self::new(data).into_instance(py)
+    }
+
+    fn freeze(mut this: PyRefMut<'_, Self>) -> PyRefMut<'_, Self> {
+        this.frozen = true;
+        this
+    }
+
+    #[getter]
+    fn name(&self) -> &'static str {
+        self.data.inspect().name()
+    }
+
+    fn is_subset_of(&self, other: PyRef<'_, Self>) -> PyResult<bool> {
+        if self.name() != other.name() {
+            return Ok(false);
+        }
+        // Only compare the rule sets themselves, not the implicit BasicCapabilities of a context.
+        let mut filter = self.to_filter()?;
+        let mut other_filter = other.to_filter()?;
+        filter.without_basics = true;
+        other_filter.without_basics = true;
+        filter
+            .covered_by(other_filter)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not compare rule sets: {err}")))
+    }
+
+    fn is_superset_of(this: PyRef<'_, Self>, other: PyRef<'_, Self>) -> PyResult<bool> {
+        other.is_subset_of(this)
+    }
+
+    fn __or__(&self, py: Python<'_>, other: &PyAny) -> PyResult<Py<PyAny>> {
+        let Ok(other) = other.extract::<PyRef<'_, PyRuleSet>>() else {
+            return Ok(py.NotImplemented());
+        };
+        Ok(self
+            .merged(py, &other)?
+            .unwrap_or_else(|| py.NotImplemented()))
+    }
+
+    fn syscalls(&self) -> PyResult<Vec<&'static str>> {
+        let filter = self.to_filter()?;
+        Ok(filter
+            .syscalls()
+            .into_iter()
+            .map(|(sysno, _)| sysno.name())
+            .collect())
+    }
+}
+
+macro_rules! enable_flag {
+    ($policy:ident, $extra_rules:ident, $flags:ident, [$enable:expr]) => {
+        $policy = $enable;
+    };
+    ($policy:ident, $extra_rules:ident, $flags:ident, { $($sysno:ident),+ $(,)? }) => {
+        $extra_rules.simple.extend([$(Sysno::$sysno),+]);
+    };
+    ($policy:ident, $extra_rules:ident, $flags:ident, ($enable:path)) => {
+        $enable(&mut $extra_rules, $flags);
+    };
+}
+
+macro_rules! set_flags {
+    ($this:ident, $py_name:ident, $func:ident, $flags:expr) => {
+        PyRuleSet::update($this, |data| {
+            let DataRuleSet::$py_name(data) = data else {
+                unreachable!("Impossible content");
+            };
+            data.flags |= $flags;
+            Ok(())
+        })
+    };
+    // A dangerous flag is only set after the confirmation was given.
+    ($this:ident, $py_name:ident, $func:ident, $flags:expr,yes_really) => {{
+        let py = $this.py();
+        Ok(YesReally::new($this, $flags.bits(), stringify!($func)).into_py(py))
+    }};
+}
 
 macro_rules! impl_subclass {
     (
@@ -120,19 +559,20 @@ macro_rules! impl_subclass {
         {
             $(
                 $(#[$flag_meta:meta])*
-                [$value:expr] $flag:ident => $func:ident [$enable:expr]
+                [$value:expr] $flag:ident => $func:ident $enable:tt $(implies $implied:ident)?
+                $(needs $confirmation:ident)?
             );* $(;)?
         }
         $extra:ty
     ) => {
         bitflags! {
-            #[derive(Debug, Default)]
-            struct $flags_name: u16 {
+            #[derive(Debug, Default, Clone, Copy)]
+            struct $flags_name: u32 {
                 $( const $flag = $value; )*
             }
         }
 
-        #[derive(Debug, Default)]
+        #[derive(Debug, Default, Clone)]
         pub(crate) struct $data_name {
             flags: $flags_name,
             #[allow(dead_code)]
@@ -140,24 +580,73 @@ macro_rules! impl_subclass {
         }
 
         impl EnablePolicy for $data_name {
-            fn enable_to(
-                &self,
-                ctx: SafetyContext,
-            ) -> Result<SafetyContext, extrasafe::ExtraSafeError> {
+            fn enable_to(&self, filter: &mut Filter) -> Result<(), extrasafe::ExtraSafeError> {
                 #[allow(unused_mut)]
                 let mut $policy = $ctor;
+                #[allow(unused_mut)]
+                let mut extra_rules = ExtraRules::new($name_str);
 
                 #[allow(unused)]
                 let $data_name { flags, extra } = self;
 
                 $(
                 if flags.contains(<$flags_name>::$flag) {
-                    $policy = $enable;
+                    enable_flag!($policy, extra_rules, flags, $enable);
                 }
                 )*
-                $policy = extra.enable_extra($policy);
+                $policy = extra.enable_extra($policy, flags, filter)?;
+
+                filter.enable(&$policy)?;
+                filter.enable(&extra_rules)
+            }
+        }
+
+        impl $data_name {
+            fn merge_from(&mut self, other: &Self) {
+                self.flags |= other.flags;
+                self.extra.merge_extra(&other.extra);
+            }
+
+            fn set_flag_bits(&mut self, bits: u32) -> bool {
+                match <$flags_name>::from_bits(bits) {
+                    Some(flags) => {
+                        self.flags = flags;
+                        true
+                    },
+                    None => false,
+                }
+            }
+        }
 
-                ctx.enable(&$policy)
+        impl InspectPolicy for $data_name {
+            #[inline]
+            fn name(&self) -> &'static str {
+                $name_str
+            }
+
+            fn flag_names(&self) -> Vec<&'static str> {
+                self.flag_names_of(self.flag_bits())
+            }
+
+            #[inline]
+            fn flag_bits(&self) -> u32 {
+                self.flags.bits()
+            }
+
+            fn flag_names_of(&self, bits: u32) -> Vec<&'static str> {
+                const NAMES: &[($flags_name, &str)] = &[
+                    $( (<$flags_name>::$flag, stringify!($func)), )*
+                ];
+                let flags = <$flags_name>::from_bits_truncate(bits);
+                NAMES
+                    .iter()
+                    .filter_map(|&(flag, name)| flags.contains(flag).then_some(name))
+                    .collect()
+            }
+
+            fn summarize_to(&self, summary: &PyDict) -> PyResult<()> {
+                merge_summary(summary, "flags", self.flag_names())?;
+                self.extra.summarize_to(summary)
             }
         }
 
@@ -166,37 +655,35 @@ macro_rules! impl_subclass {
         $(#[$meta])*
         pub(crate) struct $py_name;
 
+        impl $py_name {
+            fn wrap(py: Python<'_>, base: PyRuleSet) -> PyResult<Py<PyAny>> {
+                let init = PyClassInitializer::from(base).add_subclass(Self);
+                Ok(pyo3::PyCell::new(py, init)?.to_object(py))
+            }
+        }
+
         #[pymethods]
         impl $py_name {
             #[new]
             fn new() -> (Self, PyRuleSet) {
-                (Self, PyRuleSet(DataRuleSet::$py_name(<$data_name>::default().into())))
+                let data = DataRuleSet::$py_name(<$data_name>::default().into());
+                (Self, PyRuleSet::new(data))
             }
 
             $(
             $(#[$flag_meta])*
-            fn $func(mut this: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
-                if let DataRuleSet::$py_name(data) = &mut this.as_mut().0 {
-                    data.flags |= <$flags_name>::$flag;
-                    Ok(this)
-                } else {
-                    unreachable!("Impossible content")
-                }
+            fn $func(this: PyRefMut<'_, Self>) -> PyResult<Py<PyAny>> {
+                set_flags!(
+                    this,
+                    $py_name,
+                    $func,
+                    <$flags_name>::$flag $(| $implied)? $(, $confirmation)?
+                )
             }
             )*
 
             fn __repr__(this: PyRef<'_, Self>) -> PyResult<String> {
-                let DataRuleSet::$py_name(data) = &this.as_ref().0 else {
-                    unreachable!("Impossible content");
-                };
-
-                let mut s = String::new();
-                write!(s, "<{}({:?}{})>", $name_str, &data.flags, ReprExtra(&data.extra))
-                    .map_err(|err| {
-                        let msg = format!("could not debug??: {err}");
-                        ExtraSafeError::new_err(msg)
-                    })?;
-                Ok(s)
+                repr_policy(this.py(), this.as_ref().data.inspect())
             }
         }
     };
@@ -214,8 +701,78 @@ impl_subclass! {
     "ForkAndExec",
     PyForkAndExec,
     DataForkAndExec(FlagsForkAndExec),
-    policy: ForkAndExec = ForkAndExec => {}
-    ()
+    policy: ExtraRules = ExtraRules::new("ForkAndExec") => {}
+    ExtraForkAndExec
+}
+
+#[derive(Debug, Default, Clone)]
+struct ExtraForkAndExec {
+    wait_pids: Vec<u64>,
+}
+
+impl EnableExtra<ExtraRules, FlagsForkAndExec> for ExtraForkAndExec {
+    fn enable_extra(
+        &self,
+        mut policy: ExtraRules,
+        _: &FlagsForkAndExec,
+        _: &mut Filter,
+    ) -> Result<ExtraRules, extrasafe::ExtraSafeError> {
+        // Same as `ForkAndExec`, but reaping children might be restricted to some pids.
+        let waits = [Sysno::wait4, Sysno::waitid];
+        policy.simple.extend(
+            ForkAndExec
+                .simple_rules()
+                .into_iter()
+                .filter(|sysno| self.wait_pids.is_empty() || !waits.contains(sysno)),
+        );
+        if !self.wait_pids.is_empty() {
+            policy.allow_arg_values(Sysno::wait4, 0, &self.wait_pids);
+            let rules = policy.conditional.entry(Sysno::waitid).or_default();
+            for &pid in &self.wait_pids {
+                let rule = Rule::new(Sysno::waitid)
+                    .and_condition(ScmpArgCompare::new(
+                        0,
+                        ScmpCompareOp::Equal,
+                        libc::P_PID as u64,
+                    ))
+                    .and_condition(ScmpArgCompare::new(1, ScmpCompareOp::Equal, pid));
+                rules.push(rule);
+            }
+        }
+        Ok(policy)
+    }
+}
+
+impl MergeExtra for ExtraForkAndExec {
+    fn merge_extra(&mut self, other: &Self) {
+        merge_sorted(&mut self.wait_pids, &other.wait_pids);
+    }
+}
+
+impl SummarizeExtra for ExtraForkAndExec {
+    fn summarize_to(&self, summary: &PyDict) -> PyResult<()> {
+        merge_summary(summary, "wait_pids", &self.wait_pids)
+    }
+}
+
+#[pymethods]
+impl PyForkAndExec {
+    fn allow_wait_pid(this: PyRefMut<'_, Self>, pid: libc::pid_t) -> PyResult<Py<PyAny>> {
+        let pid = match pid > 0 {
+            true => pid as u64,
+            false => return Err(ExtraSafeError::new_err("illegal pid")),
+        };
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PyForkAndExec(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let pids = &mut data.extra.wait_pids;
+            if let Err(pos) = pids.binary_search(&pid) {
+                pids.insert(pos, pid);
+            }
+            Ok(())
+        })
+    }
 }
 
 impl_subclass! {
@@ -223,8 +780,35 @@ impl_subclass! {
     PyThreads,
     DataThreads(FlagsThreads),
     policy: Threads = Threads::nothing() => {
-        [1 << 0] ALLOW_CREATE => allow_create [policy.allow_create()];
+        [1 << 0] ALLOW_CREATE => allow_create (allow_create);
         [1 << 1] ALLOW_SLEEP => allow_sleep [policy.allow_sleep().yes_really()];
+        [1 << 2] ALLOW_SET_TID_ADDRESS => allow_set_tid_address {set_tid_address};
+    }
+    ()
+}
+
+fn allow_create(rules: &mut ExtraRules, _: &FlagsThreads) {
+    // Same as `Threads::allow_create()`, but glibc registers the tid of a new thread, so that
+    // the kernel can wake up threads in `pthread_join()` when it exits. Newer glibc versions use
+    // `clone3`, and only fall back to `clone` if the kernel returns `ENOSYS`.
+    rules
+        .simple
+        .extend([Sysno::clone, Sysno::clone3, Sysno::set_tid_address]);
+}
+
+impl_subclass! {
+    "IoUring",
+    PyIoUring,
+    DataIoUring(FlagsIoUring),
+    policy: ExtraRules = ExtraRules::new("IoUring") => {
+        [1 << 0] ALLOW_SETUP => allow_setup
+        {io_uring_setup};
+
+        [1 << 1] ALLOW_ENTER => allow_enter
+        {io_uring_enter};
+
+        [1 << 2] ALLOW_REGISTER => allow_register
+        {io_uring_register};
     }
     ()
 }
@@ -234,44 +818,468 @@ impl_subclass! {
     PyNetworking,
     DataNetworking(FlagsNetworking),
     policy: Networking = Networking::nothing() => {
+        // The grouped flags set the flags of the single syscalls that they allow, too.
         [1 << 0] ALLOW_RUNNING_TCP_CLIENTS => allow_running_tcp_clients
-        [policy.allow_running_tcp_clients()];
+        [policy.allow_running_tcp_clients()] implies RUNNING;
 
         [1 << 1] ALLOW_RUNNING_TCP_SERVERS => allow_running_tcp_servers
-        [policy.allow_running_tcp_servers()];
+        [policy.allow_running_tcp_servers()] implies RUNNING;
 
         [1 << 2] ALLOW_RUNNING_UDP_SOCKETS => allow_running_udp_sockets
-        [policy.allow_running_udp_sockets()];
+        [policy.allow_running_udp_sockets()] implies RUNNING;
 
         [1 << 3] ALLOW_RUNNING_UNIX_CLIENTS => allow_running_unix_clients
-        [policy.allow_running_unix_clients()];
+        [policy.allow_running_unix_clients()] implies RUNNING;
 
         [1 << 4] ALLOW_RUNNING_UNIX_SERVERS => allow_running_unix_servers
-        [policy.allow_running_unix_servers()];
+        [policy.allow_running_unix_servers()] implies RUNNING;
 
         [1 << 5] ALLOW_START_TCP_CLIENTS => allow_start_tcp_clients
-        [policy.allow_start_tcp_clients()];
+        (allow_start_tcp_clients) implies START_CLIENTS;
 
         [1 << 6] ALLOW_START_TCP_SERVERS => allow_start_tcp_servers
-        [policy.allow_start_tcp_servers().yes_really()];
+        (allow_start_tcp_servers) implies START_SERVERS;
 
         [1 << 7] ALLOW_START_UDP_SERVERS => allow_start_udp_servers
-        [policy.allow_start_udp_servers().yes_really()];
+        (allow_start_udp_servers) implies START_SERVERS;
 
         [1 << 8] ALLOW_START_UNIX_SERVERS => allow_start_unix_servers
-        [policy.allow_start_unix_servers().yes_really()];
+        (allow_start_unix_servers) implies START_SERVERS;
+
+        [1 << 9] ALLOW_SOCKETPAIR => allow_socketpair
+        {socketpair};
+
+        [1 << 10] ALLOW_GETSOCKOPT => allow_getsockopt
+        {getsockopt};
+
+        [1 << 11] ALLOW_SETSOCKOPT => allow_setsockopt
+        {setsockopt};
+
+        [1 << 12] ALLOW_BATCHED_UDP => allow_batched_udp
+        {recvmmsg, sendmmsg};
+
+        [1 << 13] ALLOW_SOCKET => allow_socket
+        {socket};
+
+        [1 << 14] ALLOW_BIND => allow_bind
+        {bind};
+
+        [1 << 15] ALLOW_LISTEN => allow_listen
+        {listen};
+
+        [1 << 16] ALLOW_ACCEPT => allow_accept
+        {accept, accept4};
+
+        [1 << 17] ALLOW_CONNECT => allow_connect
+        {connect};
+
+        [1 << 18] ALLOW_SHUTDOWN => allow_shutdown
+        {shutdown};
+
+        [1 << 19] ALLOW_GETSOCKNAME => allow_getsockname
+        {getsockname};
+
+        [1 << 20] ALLOW_GETPEERNAME => allow_getpeername
+        {getpeername};
+
+        // The grouped flags set this flag without a confirmation, because they allow the
+        // syscalls anyway.
+        [1 << 21] ALLOW_FD_PASSING => allow_fd_passing
+        {recvmsg, sendmsg} needs yes_really;
+    }
+    ()
+}
+
+/// The `allow_running_*()` and `allow_start_*()` flags.
+const GROUPED: FlagsNetworking = FlagsNetworking::ALLOW_RUNNING_TCP_CLIENTS
+    .union(FlagsNetworking::ALLOW_RUNNING_TCP_SERVERS)
+    .union(FlagsNetworking::ALLOW_RUNNING_UDP_SOCKETS)
+    .union(FlagsNetworking::ALLOW_RUNNING_UNIX_CLIENTS)
+    .union(FlagsNetworking::ALLOW_RUNNING_UNIX_SERVERS)
+    .union(FlagsNetworking::ALLOW_START_TCP_CLIENTS)
+    .union(FlagsNetworking::ALLOW_START_TCP_SERVERS)
+    .union(FlagsNetworking::ALLOW_START_UDP_SERVERS)
+    .union(FlagsNetworking::ALLOW_START_UNIX_SERVERS);
+
+/// The flags of the syscalls that all `allow_running_*()` and `allow_start_*()` methods allow.
+const RUNNING: FlagsNetworking = FlagsNetworking::ALLOW_GETSOCKOPT
+    .union(FlagsNetworking::ALLOW_SETSOCKOPT)
+    .union(FlagsNetworking::ALLOW_BATCHED_UDP)
+    .union(FlagsNetworking::ALLOW_LISTEN)
+    .union(FlagsNetworking::ALLOW_ACCEPT)
+    .union(FlagsNetworking::ALLOW_GETSOCKNAME)
+    .union(FlagsNetworking::ALLOW_GETPEERNAME)
+    .union(FlagsNetworking::ALLOW_FD_PASSING);
+const START_CLIENTS: FlagsNetworking = RUNNING.union(FlagsNetworking::ALLOW_CONNECT);
+const START_SERVERS: FlagsNetworking = RUNNING.union(FlagsNetworking::ALLOW_BIND);
+
+fn allow_start_tcp_clients(rules: &mut ExtraRules, flags: &FlagsNetworking) {
+    allow_start(
+        rules,
+        flags,
+        Networking::nothing().allow_start_tcp_clients(),
+    );
+}
+
+fn allow_start_tcp_servers(rules: &mut ExtraRules, flags: &FlagsNetworking) {
+    allow_start(
+        rules,
+        flags,
+        Networking::nothing().allow_start_tcp_servers().yes_really(),
+    );
+}
+
+fn allow_start_udp_servers(rules: &mut ExtraRules, flags: &FlagsNetworking) {
+    allow_start(
+        rules,
+        flags,
+        Networking::nothing().allow_start_udp_servers().yes_really(),
+    );
+}
+
+fn allow_start_unix_servers(rules: &mut ExtraRules, flags: &FlagsNetworking) {
+    allow_start(
+        rules,
+        flags,
+        Networking::nothing()
+            .allow_start_unix_servers()
+            .yes_really(),
+    );
+}
+
+fn allow_start(rules: &mut ExtraRules, flags: &FlagsNetworking, policy: Networking) {
+    rules.simple.extend(policy.simple_rules());
+
+    // `allow_socket()` allows all domains and types, so the conditional rules would conflict.
+    if !flags.contains(FlagsNetworking::ALLOW_SOCKET) {
+        for (sysno, conditional) in policy.conditional_rules() {
+            rules
+                .conditional
+                .entry(sysno)
+                .or_default()
+                .extend(conditional);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct ExtraProcessControl {
+    prctl_options: Vec<u64>,
+    madvise_advice: Vec<u64>,
+    getrandom_flags: Vec<u64>,
+}
+
+impl EnableExtra<ExtraRules, FlagsProcessControl> for ExtraProcessControl {
+    fn enable_extra(
+        &self,
+        mut policy: ExtraRules,
+        _: &FlagsProcessControl,
+        filter: &mut Filter,
+    ) -> Result<ExtraRules, extrasafe::ExtraSafeError> {
+        if !self.prctl_options.is_empty() {
+            policy.allow_arg_values(Sysno::prctl, 0, &self.prctl_options);
+        }
+        // `BasicCapabilities` allows `madvise` and `getrandom` unconditionally, so the rules would
+        // be moot.
+        for (sysno, values) in [
+            (Sysno::madvise, &self.madvise_advice),
+            (Sysno::getrandom, &self.getrandom_flags),
+        ] {
+            if values.is_empty() {
+                continue;
+            } else if !filter.without_basics {
+                return Err(extrasafe::ExtraSafeError::ConditionalNoEffectError(
+                    sysno,
+                    "ProcessControl",
+                    "BasicCapabilities",
+                ));
+            }
+            policy.allow_arg_values(sysno, 2, values);
+        }
+        Ok(policy)
+    }
+}
+
+impl MergeExtra for ExtraProcessControl {
+    fn merge_extra(&mut self, other: &Self) {
+        merge_sorted(&mut self.prctl_options, &other.prctl_options);
+        merge_sorted(&mut self.madvise_advice, &other.madvise_advice);
+        merge_sorted(&mut self.getrandom_flags, &other.getrandom_flags);
+    }
+}
+
+impl SummarizeExtra for ExtraProcessControl {
+    fn summarize_to(&self, summary: &PyDict) -> PyResult<()> {
+        merge_summary(summary, "prctl_options", &self.prctl_options)?;
+        merge_summary(summary, "madvise_advice", &self.madvise_advice)?;
+        merge_summary(summary, "getrandom_flags", &self.getrandom_flags)?;
+        Ok(())
+    }
+}
+
+impl_subclass! {
+    "ProcessControl",
+    PyProcessControl,
+    DataProcessControl(FlagsProcessControl),
+    policy: ExtraRules = ExtraRules::new("ProcessControl") => {
+        [1 << 0] ALLOW_UMASK => allow_umask
+        {umask};
+
+        [1 << 1] ALLOW_GETRUSAGE => allow_getrusage
+        {getrusage};
+
+        [1 << 2] ALLOW_ABORT => allow_abort
+        (allow_abort);
+
+        [1 << 3] ALLOW_PROCESS_VM_SELF => allow_process_vm_self
+        (allow_process_vm_self);
+
+        [1 << 4] ALLOW_PERSONALITY_QUERY => allow_personality_query
+        (allow_personality_query);
+    }
+    ExtraProcessControl
+}
+
+fn allow_abort(rules: &mut ExtraRules, _: &FlagsProcessControl) {
+    // `abort()` sends `SIGABRT` to the current thread, so only allow this signal and this process.
+    let pid = getpid().as_raw_nonzero().get() as u64;
+    let rule = Rule::new(Sysno::tgkill)
+        .and_condition(ScmpArgCompare::new(0, ScmpCompareOp::Equal, pid))
+        .and_condition(ScmpArgCompare::new(
+            2,
+            ScmpCompareOp::Equal,
+            libc::SIGABRT as u64,
+        ));
+    rules
+        .conditional
+        .entry(Sysno::tgkill)
+        .or_default()
+        .push(rule);
+}
+
+fn allow_process_vm_self(rules: &mut ExtraRules, _: &FlagsProcessControl) {
+    // Only reading the own memory is harmless, reading other processes could leak their secrets.
+    let pid = getpid().as_raw_nonzero().get() as u64;
+    rules.allow_arg_values(Sysno::process_vm_readv, 0, &[pid]);
+}
+
+fn allow_personality_query(rules: &mut ExtraRules, _: &FlagsProcessControl) {
+    // The kernel only looks at the lower 32 bits, and `0xffffffff` reads the persona without
+    // changing it. Any other value sets it, e.g. `ADDR_NO_RANDOMIZE` disables ASLR.
+    let rule = Rule::new(Sysno::personality).and_condition(ScmpArgCompare::new(
+        0,
+        ScmpCompareOp::MaskedEqual(0xffff_ffff),
+        0xffff_ffff,
+    ));
+    rules
+        .conditional
+        .entry(Sysno::personality)
+        .or_default()
+        .push(rule);
+}
+
+#[pymethods]
+impl PyProcessControl {
+    fn allow_prctl_option(this: PyRefMut<'_, Self>, option: libc::c_int) -> PyResult<Py<PyAny>> {
+        let option =
+            u64::try_from(option).map_err(|_| ExtraSafeError::new_err("illegal prctl option"))?;
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PyProcessControl(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let options = &mut data.extra.prctl_options;
+            if let Err(pos) = options.binary_search(&option) {
+                options.insert(pos, option);
+            }
+            Ok(())
+        })
+    }
+
+    fn allow_madvise_advice(this: PyRefMut<'_, Self>, advice: libc::c_int) -> PyResult<Py<PyAny>> {
+        let advice =
+            u64::try_from(advice).map_err(|_| ExtraSafeError::new_err("illegal madvise advice"))?;
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PyProcessControl(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let madvise_advice = &mut data.extra.madvise_advice;
+            if let Err(pos) = madvise_advice.binary_search(&advice) {
+                madvise_advice.insert(pos, advice);
+            }
+            Ok(())
+        })
+    }
+
+    fn allow_getrandom_flags(this: PyRefMut<'_, Self>, flags: libc::c_uint) -> PyResult<Py<PyAny>> {
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PyProcessControl(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let flags = u64::from(flags);
+            let getrandom_flags = &mut data.extra.getrandom_flags;
+            if let Err(pos) = getrandom_flags.binary_search(&flags) {
+                getrandom_flags.insert(pos, flags);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl_subclass! {
+    "Credentials",
+    PyCredentials,
+    DataCredentials(FlagsCredentials),
+    policy: ExtraRules = ExtraRules::new("Credentials") => {
+        [1 << 0] ALLOW_GETGROUPS => allow_getgroups
+        {getgroups};
+
+        [1 << 1] ALLOW_SETGROUPS => allow_setgroups
+        {setgroups};
+    }
+    ()
+}
+
+impl_subclass! {
+    "MountNamespace",
+    PyMountNamespace,
+    DataMountNamespace(FlagsMountNamespace),
+    policy: ExtraRules = ExtraRules::new("MountNamespace") => {
+        [1 << 0] ALLOW_MOUNT => allow_mount
+        {mount} needs yes_really;
+
+        [1 << 1] ALLOW_UMOUNT => allow_umount
+        {umount2} needs yes_really;
+
+        [1 << 2] ALLOW_PIVOT_ROOT => allow_pivot_root
+        {pivot_root} needs yes_really;
+    }
+    ()
+}
+
+/// A dangerous flag that is only set after [`YesReally::yes_really()`] was called, like
+/// `extrasafe::builtins::YesReally` does it.
+#[pyclass]
+#[pyo3(name = "_YesReally", module = "pyextrasafe")]
+#[derive(Debug)]
+pub(crate) struct YesReally {
+    policy: Py<PyAny>,
+    flags: u32,
+    name: &'static str,
+}
+
+impl YesReally {
+    fn new<T: PyClass<Frozen = False>>(
+        this: PyRefMut<'_, T>,
+        flags: u32,
+        name: &'static str,
+    ) -> Self {
+        let py = this.py();
+        Self {
+            policy: this.into_py(py),
+            flags,
+            name,
+        }
+    }
+}
+
+#[pymethods]
+impl YesReally {
+    fn yes_really(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let cell: &PyCell<PyRuleSet> = self.policy.as_ref(py).downcast()?;
+        let mut policy = cell.try_borrow_mut()?;
+        if policy.frozen {
+            let mut copy = policy.clone();
+            copy.add_flag_bits(self.flags);
+            copy.into_instance(py)
+        } else {
+            policy.add_flag_bits(self.flags);
+            Ok(self.policy.clone_ref(py))
+        }
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let policy = self.policy.as_ref(py).repr()?;
+        Ok(format!("<YesReally {}.{}()>", policy.to_str()?, self.name))
+    }
+}
+
+impl_subclass! {
+    "PidFd",
+    PyPidFd,
+    DataPidFd(FlagsPidFd),
+    policy: ExtraRules = ExtraRules::new("PidFd") => {
+        [1 << 0] ALLOW_PIDFD_OPEN => allow_pidfd_open
+        {pidfd_open};
+
+        [1 << 1] ALLOW_PIDFD_SEND_SIGNAL => allow_pidfd_send_signal
+        {pidfd_send_signal};
+
+        [1 << 2] ALLOW_PIDFD_GETFD => allow_pidfd_getfd
+        {pidfd_getfd};
+    }
+    ()
+}
+
+impl_subclass! {
+    "Scheduling",
+    PyScheduling,
+    DataScheduling(FlagsScheduling),
+    policy: ExtraRules = ExtraRules::new("Scheduling") => {
+        [1 << 0] ALLOW_GETPRIORITY => allow_getpriority
+        {getpriority};
+
+        [1 << 1] ALLOW_SETPRIORITY => allow_setpriority
+        {setpriority};
+
+        [1 << 2] ALLOW_SCHED_GETSCHEDULER => allow_sched_getscheduler
+        {sched_getscheduler, sched_getparam, sched_get_priority_min, sched_get_priority_max};
+
+        [1 << 3] ALLOW_SCHED_SETSCHEDULER => allow_sched_setscheduler
+        {sched_setscheduler, sched_setparam};
+
+        [1 << 4] ALLOW_GETCPU => allow_getcpu
+        {getcpu};
     }
     ()
 }
 
-#[derive(Debug, Default)]
-struct ReadWriteFilenos {
+/// The syscalls of [`PySystemIO::allow_low_fds()`], which all get the file descriptor as their first
+/// argument, with the flag that allows them for any file descriptor.
+const LOW_FDS_SYSCALLS: [(Sysno, FlagsSystemIO); 10] = [
+    (Sysno::read, FlagsSystemIO::ALLOW_READ),
+    (Sysno::readv, FlagsSystemIO::ALLOW_READ),
+    (Sysno::pread64, FlagsSystemIO::ALLOW_READ),
+    (Sysno::preadv, FlagsSystemIO::ALLOW_READ),
+    (Sysno::preadv2, FlagsSystemIO::ALLOW_READ),
+    (Sysno::write, FlagsSystemIO::ALLOW_WRITE),
+    (Sysno::writev, FlagsSystemIO::ALLOW_WRITE),
+    (Sysno::pwrite64, FlagsSystemIO::ALLOW_WRITE),
+    (Sysno::pwritev, FlagsSystemIO::ALLOW_WRITE),
+    (Sysno::pwritev2, FlagsSystemIO::ALLOW_WRITE),
+];
+
+#[derive(Debug, Default, Clone)]
+struct ExtraSystemIO {
     rd: Vec<RawFd>,
     wr: Vec<RawFd>,
+    open_within: Option<Vec<PathBuf>>,
+    rename_flags: Vec<u64>,
+    fcntl_cmds: Vec<u64>,
+    low_fds: Option<u64>,
+    /// The flags that must be clear, and the flags that must be set.
+    open_flags: Vec<(u64, u64)>,
 }
 
-impl EnableExtra<SystemIO> for ReadWriteFilenos {
-    fn enable_extra(&self, mut policy: SystemIO) -> SystemIO {
+impl EnableExtra<SystemIO, FlagsSystemIO> for ExtraSystemIO {
+    fn enable_extra(
+        &self,
+        mut policy: SystemIO,
+        flags: &FlagsSystemIO,
+        filter: &mut Filter,
+    ) -> Result<SystemIO, extrasafe::ExtraSafeError> {
+        if let Some(open_within) = &self.open_within {
+            filter.restrict_paths(open_within);
+        }
         for &fileno in &self.rd {
             let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fileno) });
             policy = policy.allow_file_read(&file);
@@ -280,7 +1288,72 @@ impl EnableExtra<SystemIO> for ReadWriteFilenos {
             let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fileno) });
             policy = policy.allow_file_write(&file);
         }
-        policy
+        // A flag that allows a syscall unconditionally makes its conditional rules moot, and
+        // mixing both kinds would conflict, so these rules are skipped.
+        let mut rules = ExtraRules::new("SystemIO");
+        if !self.rename_flags.is_empty() && !flags.contains(FlagsSystemIO::ALLOW_RENAME) {
+            rules.allow_arg_values(Sysno::renameat2, 4, &self.rename_flags);
+        }
+        if !self.fcntl_cmds.is_empty() && !flags.contains(FlagsSystemIO::ALLOW_IOCTL) {
+            rules.allow_arg_values(Sysno::fcntl, 1, &self.fcntl_cmds);
+        }
+        if let Some(limit) = self.low_fds {
+            for (sysno, flag) in LOW_FDS_SYSCALLS {
+                if flags.contains(flag) {
+                    continue;
+                }
+                let comparator = ScmpArgCompare::new(0, ScmpCompareOp::Less, limit);
+                let rule = Rule::new(sysno).and_condition(comparator);
+                rules.conditional.entry(sysno).or_default().push(rule);
+            }
+        }
+        if !flags.contains(FlagsSystemIO::ALLOW_OPEN) {
+            // The flags are the second argument of `open`, but the third of `openat`.
+            for (sysno, arg) in [(Sysno::open, 1), (Sysno::openat, 2)] {
+                for &(clear, set) in &self.open_flags {
+                    let comparator =
+                        ScmpArgCompare::new(arg, ScmpCompareOp::MaskedEqual(clear | set), set);
+                    let rule = Rule::new(sysno).and_condition(comparator);
+                    rules.conditional.entry(sysno).or_default().push(rule);
+                }
+            }
+        }
+        filter.enable(&rules)?;
+        Ok(policy)
+    }
+}
+
+impl MergeExtra for ExtraSystemIO {
+    fn merge_extra(&mut self, other: &Self) {
+        merge_sorted(&mut self.rd, &other.rd);
+        merge_sorted(&mut self.wr, &other.wr);
+        if let Some(paths) = &other.open_within {
+            let open_within = self.open_within.get_or_insert_with(Vec::new);
+            for path in paths {
+                if !open_within.contains(path) {
+                    open_within.push(path.clone());
+                }
+            }
+        }
+        merge_sorted(&mut self.rename_flags, &other.rename_flags);
+        merge_sorted(&mut self.fcntl_cmds, &other.fcntl_cmds);
+        self.low_fds = self.low_fds.max(other.low_fds);
+        merge_sorted(&mut self.open_flags, &other.open_flags);
+    }
+}
+
+impl SummarizeExtra for ExtraSystemIO {
+    fn summarize_to(&self, summary: &PyDict) -> PyResult<()> {
+        merge_summary(summary, "read_fds", &self.rd)?;
+        merge_summary(summary, "write_fds", &self.wr)?;
+        if let Some(open_within) = &self.open_within {
+            merge_summary(summary, "open_within", open_within)?;
+        }
+        merge_summary(summary, "rename_flags", &self.rename_flags)?;
+        merge_summary(summary, "fcntl_cmds", &self.fcntl_cmds)?;
+        merge_summary(summary, "low_fds", self.low_fds)?;
+        merge_summary(summary, "open_flags", &self.open_flags)?;
+        Ok(())
     }
 }
 
@@ -318,8 +1391,143 @@ impl_subclass! {
 
         [1 << 9] ALLOW_WRITE => allow_write
         [policy.allow_write()];
+
+        [1 << 10] ALLOW_PIPE => allow_pipe
+        {pipe, pipe2};
+
+        [1 << 11] ALLOW_STATFS => allow_statfs
+        {statfs, fstatfs};
+
+        [1 << 12] ALLOW_OPENAT2 => allow_openat2
+        {openat2};
+
+        [1 << 13] ALLOW_POLL => allow_poll
+        {
+            poll, ppoll, select, pselect6,
+            epoll_create, epoll_create1, epoll_ctl, epoll_wait, epoll_pwait, epoll_pwait2,
+        };
+
+        [1 << 14] ALLOW_FILE_LOCKING => allow_file_locking
+        (allow_file_locking);
+
+        [1 << 15] ALLOW_TRUNCATE => allow_truncate
+        {truncate, ftruncate};
+
+        [1 << 16] ALLOW_FALLOCATE => allow_fallocate
+        {fallocate};
+
+        [1 << 17] ALLOW_FADVISE => allow_fadvise
+        {fadvise64};
+
+        [1 << 18] ALLOW_SYNC_FILE_RANGE => allow_sync_file_range
+        {sync_file_range};
+
+        [1 << 19] ALLOW_RENAME => allow_rename
+        {rename, renameat, renameat2};
+
+        [1 << 20] ALLOW_SCANDIR => allow_scandir
+        (allow_scandir);
+
+        [1 << 21] ALLOW_MEMFD => allow_memfd
+        {memfd_create};
+
+        [1 << 22] ALLOW_MEMFD_NOEXEC => allow_memfd_noexec
+        (allow_memfd_noexec);
+
+        [1 << 23] ALLOW_MKFIFO => allow_mkfifo
+        (allow_mkfifo);
+
+        [1 << 24] ALLOW_INOTIFY => allow_inotify
+        {inotify_init, inotify_init1, inotify_add_watch, inotify_rm_watch};
+
+        [1 << 25] ALLOW_DUP => allow_dup
+        {dup};
+
+        [1 << 26] ALLOW_DUP2 => allow_dup2
+        {dup2, dup3};
+
+        [1 << 27] ALLOW_FCHMOD => allow_fchmod
+        {fchmod};
+
+        [1 << 28] ALLOW_FCHOWN => allow_fchown
+        {fchown};
+    }
+    ExtraSystemIO
+}
+
+fn allow_mkfifo(rules: &mut ExtraRules, _: &FlagsSystemIO) {
+    // A file type of `0` creates a regular file, too. Device nodes must never be allowed.
+    const FILE_TYPES: &[libc::mode_t] = &[0, libc::S_IFREG, libc::S_IFIFO];
+
+    for (sysno, arg) in [(Sysno::mknod, 1), (Sysno::mknodat, 2)] {
+        let entry = rules.conditional.entry(sysno).or_default();
+        for &file_type in FILE_TYPES {
+            entry.push(Rule::new(sysno).and_condition(ScmpArgCompare::new(
+                arg,
+                ScmpCompareOp::MaskedEqual(libc::S_IFMT.into()),
+                file_type.into(),
+            )));
+        }
+    }
+}
+
+fn allow_memfd_noexec(rules: &mut ExtraRules, flags: &FlagsSystemIO) {
+    // Without `MFD_NOEXEC_SEAL` the kernel might create an executable memfd by default.
+    const MASK: u64 = (libc::MFD_EXEC | libc::MFD_NOEXEC_SEAL) as u64;
+
+    // `allow_memfd()` allows all flags, so a conditional rule would conflict.
+    if !flags.contains(FlagsSystemIO::ALLOW_MEMFD) {
+        let rule = Rule::new(Sysno::memfd_create).and_condition(ScmpArgCompare::new(
+            1,
+            ScmpCompareOp::MaskedEqual(MASK),
+            libc::MFD_NOEXEC_SEAL as u64,
+        ));
+        rules
+            .conditional
+            .entry(Sysno::memfd_create)
+            .or_default()
+            .push(rule);
+    }
+}
+
+fn allow_file_locking(rules: &mut ExtraRules, flags: &FlagsSystemIO) {
+    const LOCK_CMDS: &[libc::c_int] = &[
+        libc::F_GETLK,
+        libc::F_SETLK,
+        libc::F_SETLKW,
+        libc::F_OFD_GETLK,
+        libc::F_OFD_SETLK,
+        libc::F_OFD_SETLKW,
+    ];
+
+    rules.simple.push(Sysno::flock);
+    // `allow_ioctl()` allows all fcntl commands, so a conditional rule would conflict.
+    if !flags.contains(FlagsSystemIO::ALLOW_IOCTL) {
+        let cmds = LOCK_CMDS.iter().map(|&cmd| cmd as u64).collect::<Vec<_>>();
+        rules.allow_arg_values(Sysno::fcntl, 1, &cmds);
+    }
+}
+
+fn allow_scandir(rules: &mut ExtraRules, flags: &FlagsSystemIO) {
+    // `os.scandir(fd)` duplicates the file descriptor, and `fdopendir()` checks its mode.
+    const SCANDIR_CMDS: &[libc::c_int] = &[libc::F_DUPFD_CLOEXEC, libc::F_GETFL, libc::F_GETFD];
+
+    rules.simple.extend([
+        Sysno::getdents64,
+        Sysno::fstat,
+        Sysno::newfstatat,
+        Sysno::statx,
+        Sysno::lseek,
+        Sysno::close,
+    ]);
+    // `allow_ioctl()` allows all fcntl commands, so a conditional rule would conflict.
+    if !flags.contains(FlagsSystemIO::ALLOW_IOCTL) {
+        let cmds = SCANDIR_CMDS
+            .iter()
+            .map(|&cmd| cmd as u64)
+            .collect::<Vec<_>>();
+        rules.allow_arg_values(Sysno::fcntl, 1, &cmds);
     }
-    ReadWriteFilenos
 }
 
 #[pymethods]
@@ -330,37 +1538,129 @@ impl PySystemIO {
             flags: FlagsSystemIO::all(),
             extra: Default::default(),
         };
-        let value = PyRuleSet(DataRuleSet::PySystemIO(value.into()));
-        let init = PyClassInitializer::from(value).add_subclass(Self);
-        Ok(pyo3::PyCell::new(py, init)?.to_object(py))
+        Self::wrap(py, PyRuleSet::new(DataRuleSet::PySystemIO(value.into())))
     }
 
-    fn allow_file_read(
-        mut this: PyRefMut<'_, Self>,
-        fileno: RawFd,
-    ) -> PyResult<PyRefMut<'_, Self>> {
-        if let DataRuleSet::PySystemIO(data) = &mut this.as_mut().0 {
+    fn allow_file_read(this: PyRefMut<'_, Self>, fileno: RawFd) -> PyResult<Py<PyAny>> {
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
             insert_sorted_fileno(&mut data.extra.rd, fileno)?;
-            Ok(this)
-        } else {
-            unreachable!("Impossible content")
-        }
+            Ok(())
+        })
     }
 
-    fn allow_file_write(
-        mut this: PyRefMut<'_, Self>,
-        fileno: RawFd,
-    ) -> PyResult<PyRefMut<'_, Self>> {
-        if let DataRuleSet::PySystemIO(data) = &mut this.as_mut().0 {
+    fn allow_file_write(this: PyRefMut<'_, Self>, fileno: RawFd) -> PyResult<Py<PyAny>> {
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
             insert_sorted_fileno(&mut data.extra.wr, fileno)?;
-            Ok(this)
-        } else {
-            unreachable!("Impossible content")
+            Ok(())
+        })
+    }
+
+    fn allow_open_within(this: PyRefMut<'_, Self>, paths: Vec<PathBuf>) -> PyResult<Py<PyAny>> {
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
+            data.flags |= FlagsSystemIO::ALLOW_OPEN;
+            data.extra
+                .open_within
+                .get_or_insert_with(Vec::new)
+                .extend(paths);
+            Ok(())
+        })
+    }
+
+    fn is_file_read_allowed(this: PyRef<'_, Self>, fileno: RawFd) -> bool {
+        let DataRuleSet::PySystemIO(data) = &this.as_ref().data else {
+            unreachable!("Impossible content");
+        };
+        data.extra.rd.binary_search(&fileno).is_ok()
+    }
+
+    fn is_file_write_allowed(this: PyRef<'_, Self>, fileno: RawFd) -> bool {
+        let DataRuleSet::PySystemIO(data) = &this.as_ref().data else {
+            unreachable!("Impossible content");
+        };
+        data.extra.wr.binary_search(&fileno).is_ok()
+    }
+
+    fn allow_rename_flags(this: PyRefMut<'_, Self>, flags: libc::c_uint) -> PyResult<Py<PyAny>> {
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let flags = u64::from(flags);
+            let rename_flags = &mut data.extra.rename_flags;
+            if let Err(pos) = rename_flags.binary_search(&flags) {
+                rename_flags.insert(pos, flags);
+            }
+            Ok(())
+        })
+    }
+
+    fn allow_low_fds(this: PyRefMut<'_, Self>, limit: RawFd) -> PyResult<Py<PyAny>> {
+        let limit = match limit > 0 {
+            true => limit as u64,
+            false => return Err(ExtraSafeError::new_err("illegal limit")),
+        };
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
+            data.extra.low_fds = data.extra.low_fds.max(Some(limit));
+            Ok(())
+        })
+    }
+
+    fn allow_fcntl_cmd(this: PyRefMut<'_, Self>, cmd: libc::c_int) -> PyResult<Py<PyAny>> {
+        let cmd =
+            u64::try_from(cmd).map_err(|_| ExtraSafeError::new_err("illegal fcntl command"))?;
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let fcntl_cmds = &mut data.extra.fcntl_cmds;
+            if let Err(pos) = fcntl_cmds.binary_search(&cmd) {
+                fcntl_cmds.insert(pos, cmd);
+            }
+            Ok(())
+        })
+    }
+
+    #[pyo3(signature = (required_clear=0, required_set=0))]
+    fn allow_open_flags(
+        this: PyRefMut<'_, Self>,
+        required_clear: libc::c_int,
+        required_set: libc::c_int,
+    ) -> PyResult<Py<PyAny>> {
+        let (Ok(clear), Ok(set)) = (u64::try_from(required_clear), u64::try_from(required_set))
+        else {
+            return Err(ExtraSafeError::new_err("illegal open flags"));
+        };
+        if clear & set != 0 {
+            return Err(ExtraSafeError::new_err(
+                "illegal open flags: a flag cannot be required to be clear and set",
+            ));
         }
+        PyRuleSet::update(this, |data| {
+            let DataRuleSet::PySystemIO(data) = data else {
+                unreachable!("Impossible content");
+            };
+            let open_flags = &mut data.extra.open_flags;
+            if let Err(pos) = open_flags.binary_search(&(clear, set)) {
+                open_flags.insert(pos, (clear, set));
+            }
+            Ok(())
+        })
     }
 }
 
-fn insert_sorted_fileno(vec: &mut Vec<RawFd>, fileno: RawFd) -> PyResult<()> {
+pub(crate) fn insert_sorted_fileno(vec: &mut Vec<RawFd>, fileno: RawFd) -> PyResult<()> {
     if fileno < 0 {
         return Err(ExtraSafeError::new_err("illegal fileno"));
     }
@@ -377,6 +1677,37 @@ impl_subclass! {
     policy: Time = Time::nothing() => {
         [1 << 0] ALLOW_GETTIME => allow_gettime
         [policy.allow_gettime()];
+
+        // The mode is a field of `struct timex`, which seccomp cannot inspect, so reading cannot
+        // be told apart from adjusting. So the flag needs a confirmation.
+        [1 << 1] ALLOW_ADJTIME => allow_adjtime
+        {adjtimex, clock_adjtime} needs yes_really;
+
+        [1 << 2] ALLOW_TIMERFD => allow_timerfd
+        {timerfd_create, timerfd_gettime, timerfd_settime};
+
+        [1 << 3] ALLOW_TIMERFD_RELATIVE => allow_timerfd_relative
+        (allow_timerfd_relative);
     }
     ()
 }
+
+fn allow_timerfd_relative(rules: &mut ExtraRules, flags: &FlagsTime) {
+    rules
+        .simple
+        .extend([Sysno::timerfd_create, Sysno::timerfd_gettime]);
+
+    // `allow_timerfd()` allows all flags, so a conditional rule would conflict.
+    if !flags.contains(FlagsTime::ALLOW_TIMERFD) {
+        let rule = Rule::new(Sysno::timerfd_settime).and_condition(ScmpArgCompare::new(
+            1,
+            ScmpCompareOp::MaskedEqual(libc::TFD_TIMER_ABSTIME as u64),
+            0,
+        ));
+        rules
+            .conditional
+            .entry(Sysno::timerfd_settime)
+            .or_default()
+            .push(rule);
+    }
+}
@@ -1,60 +1,1424 @@
-use extrasafe::SafetyContext;
-use pyo3::{pyclass, pymethods, Py, PyRef, PyRefMut, PyResult, Python};
+use std::collections::{HashMap, HashSet};
+use std::env::VarError;
+use std::ffi::{CString, OsString};
+use std::fs::{read_dir, read_to_string, write, File, OpenOptions};
+use std::io::{self, Write};
+use std::mem::ManuallyDrop;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::ptr::null;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::rule_sets::{EnablePolicy, PyRuleSet};
-use crate::ExtraSafeError;
+use extrasafe::builtins::{BasicCapabilities, SystemIO, Time};
+use libseccomp::error::SeccompErrno;
+use libseccomp::{ScmpAction, ScmpFilterContext};
+use pyo3::exceptions::PyTypeError;
+use pyo3::types::{PyBytes, PyDict, PyList, PyString, PyTuple};
+use pyo3::{pyclass, pyfunction, pymethods, Py, PyAny, PyErr, PyRef, PyRefMut, PyResult, Python};
+use rustix::io::Errno;
+use rustix::process::getpid;
+use rustix::thread::{self, gettid};
+use syscalls::Sysno;
+
+use crate::filter::{export_bpf, sock_filters, Filter};
+use crate::landlock::Ruleset;
+use crate::notify::spawn_supervisor;
+use crate::remote::{install_on_threads, ThreadError};
+use crate::rule_sets::{
+    insert_sorted_fileno, DataRuleSet, EnablePolicy, ExtraRules, InspectPolicy, PyRuleSet,
+};
+use crate::{probe, ApplyError, ExtraSafeError};
 
 #[pyclass]
 #[pyo3(name = "SafetyContext", module = "pyextrasafe")]
 #[derive(Debug)]
-pub(crate) struct PySafetyContext(Vec<Py<PyRuleSet>>);
+pub(crate) struct PySafetyContext {
+    policies: Vec<Py<PyRuleSet>>,
+    /// Raw syscall numbers from [`PySafetyContext::from_syscall_numbers()`].
+    syscall_numbers: Option<Vec<Sysno>>,
+    default_action: Option<ScmpAction>,
+    /// File descriptors from [`PySafetyContext::allow_file_read()`], merged into all SystemIO.
+    read_fds: Vec<RawFd>,
+    /// File descriptors from [`PySafetyContext::allow_file_write()`], merged into all SystemIO.
+    write_fds: Vec<RawFd>,
+    /// Syscalls from [`PySafetyContext::forbid()`].
+    forbidden: Vec<Sysno>,
+    /// Syscalls from [`PySafetyContext::notify()`].
+    notified: Vec<Sysno>,
+    /// The name from the constructor, for representations and error messages.
+    name: Option<String>,
+    /// Don't allow enabling two rule sets of the same type.
+    strict_duplicates: bool,
+    /// Set by [`PySafetyContext::freeze()`].
+    frozen: bool,
+    /// Enable [`extrasafe::builtins::BasicCapabilities`] implicitly.
+    include_basics: bool,
+    /// The BPF program of the most recently compiled filter, and the filter it was compiled from.
+    compiled: Option<(String, Vec<u8>)>,
+    /// How often [`PySafetyContext::apply()`] compiled the filter, so tests can check the cache.
+    compile_count: usize,
+    last_applied_bpf: Option<Vec<u8>>,
+}
 
 impl PySafetyContext {
-    fn to_context(&self, py: Python<'_>) -> PyResult<SafetyContext> {
-        let mut ctx = SafetyContext::new();
-        for policy in &self.0 {
+    pub(crate) fn new() -> Self {
+        Self {
+            policies: Vec::new(),
+            syscall_numbers: None,
+            default_action: None,
+            read_fds: Vec::new(),
+            write_fds: Vec::new(),
+            forbidden: Vec::new(),
+            notified: Vec::new(),
+            name: None,
+            strict_duplicates: false,
+            frozen: false,
+            include_basics: true,
+            compiled: None,
+            compile_count: 0,
+            last_applied_bpf: None,
+        }
+    }
+
+    pub(crate) fn to_filter(&self, py: Python<'_>) -> PyResult<Filter> {
+        let mut filter = Filter::default();
+        filter.default_action = self.default_action;
+        filter.forbidden.clone_from(&self.forbidden);
+        filter.notified.clone_from(&self.notified);
+        filter.without_basics = !self.include_basics;
+        if let Some(syscall_numbers) = &self.syscall_numbers {
+            filter
+                .enable(syscall_numbers_rules(syscall_numbers))
+                .map_err(|err| {
+                    ExtraSafeError::new_err(format!("syscall numbers could not be applied: {err}"))
+                })?;
+            filter.without_basics = true;
+        }
+        for policy in &self.policies {
             let policy = &*policy.borrow(py);
-            ctx = policy.enable_to(ctx).map_err(|err| {
+            policy.enable_to(&mut filter).map_err(|err| {
                 ExtraSafeError::new_err(format!("policy {policy:?} could not be applied: {err}"))
             })?;
         }
+        if !self.read_fds.is_empty() || !self.write_fds.is_empty() {
+            let mut policy = SystemIO::nothing();
+            for &fileno in &self.read_fds {
+                let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fileno) });
+                policy = policy.allow_file_read(&file);
+            }
+            for &fileno in &self.write_fds {
+                let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fileno) });
+                policy = policy.allow_file_write(&file);
+            }
+            // Rules for syscalls that some SystemIO allows unconditionally are redundant.
+            filter.enable_unless_allowed(policy).map_err(|err| {
+                ExtraSafeError::new_err(format!("file descriptors could not be applied: {err}"))
+            })?;
+        }
+        Ok(filter)
+    }
+
+    /// Build a context from a JSON object of rule set names to lists of flags. `source` is where
+    /// the JSON came from, for error messages.
+    fn from_spec(py: Python<'_>, spec: &str, source: &str) -> PyResult<Self> {
+        let spec = py
+            .import("json")?
+            .call_method1("loads", (spec,))
+            .map_err(|err| {
+                ExtraSafeError::new_err(format!(
+                    "{source} does not contain valid JSON: {}",
+                    err.value(py),
+                ))
+            })?;
+        let spec: HashMap<String, Vec<String>> = spec.extract().map_err(|_| {
+            ExtraSafeError::new_err(format!(
+                "{source} does not contain an object of rule set names to lists of flags",
+            ))
+        })?;
+
+        let mut ctx = Self::new();
+        let mut names = spec.keys().collect::<Vec<_>>();
+        names.sort_unstable();
+        for name in names {
+            let flags = spec[name].iter().map(String::as_str).collect::<Vec<_>>();
+            ctx.policies
+                .push(Self::named_policy(py, name, &flags, source)?);
+        }
         Ok(ctx)
     }
+
+    /// A new rule set of the type `name` with the `flags` set. `source` is where the names came
+    /// from, for error messages.
+    fn named_policy(
+        py: Python<'_>,
+        name: &str,
+        flags: &[&str],
+        source: &str,
+    ) -> PyResult<Py<PyRuleSet>> {
+        let policy = PyRuleSet::new_named(py, &name.to_lowercase())?.ok_or_else(|| {
+            ExtraSafeError::new_err(format!("unknown rule set {name:?} in {source}"))
+        })?;
+        let known = policy
+            .extract::<PyRef<'_, PyRuleSet>>(py)?
+            .data
+            .inspect()
+            .flag_names_of(u32::MAX);
+        let mut unconfirmed = Vec::new();
+        for &flag in flags {
+            if !known.contains(&flag) {
+                return Err(ExtraSafeError::new_err(format!(
+                    "unknown flag {flag:?} of rule set {name:?} in {source}"
+                )));
+            }
+            // Dangerous flags return a confirmation instead, which must be given in the code.
+            let result = policy.call_method0(py, flag)?;
+            if !result.as_ref(py).is_instance_of::<PyRuleSet>() {
+                unconfirmed.push(flag);
+            }
+        }
+        // Unless another flag sets it too, e.g. `allow_fd_passing` from `allow_running_*`.
+        let set = policy
+            .extract::<PyRef<'_, PyRuleSet>>(py)?
+            .data
+            .inspect()
+            .flag_names();
+        if let Some(flag) = unconfirmed.into_iter().find(|flag| !set.contains(flag)) {
+            return Err(ExtraSafeError::new_err(format!(
+                "flag {flag:?} of rule set {name:?} needs a confirmation, \
+                it cannot be set in {source}"
+            )));
+        }
+        policy.extract(py)
+    }
+
+    /// The policy as JSON, in the format of [`PySafetyContext::from_spec()`].
+    fn to_spec(&self, py: Python<'_>) -> PyResult<String> {
+        let unrepresentable = |what: &str| {
+            ExtraSafeError::new_err(format!(
+                "the context cannot be written as JSON, because it has {what}"
+            ))
+        };
+        if self.syscall_numbers.is_some() {
+            return Err(unrepresentable("raw syscall numbers"));
+        } else if !self.read_fds.is_empty() || !self.write_fds.is_empty() {
+            return Err(unrepresentable("file descriptors"));
+        } else if !self.forbidden.is_empty() {
+            return Err(unrepresentable("forbidden syscalls"));
+        } else if !self.notified.is_empty() {
+            return Err(unrepresentable("notified syscalls"));
+        } else if !self.include_basics {
+            return Err(unrepresentable("no implicit BasicCapabilities"));
+        } else if !matches!(self.default_action, None | Some(ScmpAction::Errno(_))) {
+            return Err(unrepresentable("an on_violation action"));
+        }
+        for (name, entry) in self.summary(py)?.as_ref(py) {
+            let name = name.extract::<&str>()?;
+            // Only the flags are written, so all other entries must be empty.
+            for (key, items) in entry.downcast::<PyDict>()? {
+                if key.extract::<&str>()? != "flags" && items.is_true()? {
+                    return Err(unrepresentable(&format!("{key} in {name}")));
+                }
+            }
+        }
+        for policy in &self.policies {
+            let data = &policy.borrow(py).data;
+            if let Some(flag) = data.confirmed_flag() {
+                let name = data.inspect().name();
+                return Err(unrepresentable(&format!("{flag} in {name}")));
+            }
+        }
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("sort_keys", true)?;
+        py.import("json")?
+            .call_method("dumps", (self.effective_flags(py)?,), Some(kwargs))?
+            .extract()
+    }
+
+    /// The BPF program of the filter, without loading it.
+    fn compile_bpf(&self, py: Python<'_>) -> PyResult<Vec<u8>> {
+        let ctx = self
+            .to_filter(py)?
+            .compile(false)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not compile filter: {err}")))?;
+        export_bpf(&ctx)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not export filter: {err}")))
+    }
+
+    fn allowed_syscalls(&self, py: Python<'_>) -> PyResult<Vec<Sysno>> {
+        self.to_filter(py)?
+            .allowed_syscalls()
+            .map_err(|err| ExtraSafeError::new_err(format!("could not list syscalls: {err}")))
+    }
+
+    /// Fail if the context was frozen with [`PySafetyContext::freeze()`].
+    fn ensure_mutable(&self, py: Python<'_>) -> PyResult<()> {
+        match self.frozen {
+            false => Ok(()),
+            true => Err(self.named_err(
+                py,
+                ExtraSafeError::new_err("the context is frozen, so it cannot be modified"),
+            )),
+        }
+    }
+
+    /// Prefix the message of `err` with the name of the context, if it has one.
+    fn named_err(&self, py: Python<'_>, err: PyErr) -> PyErr {
+        let Some(name) = &self.name else {
+            return err;
+        };
+        let value = err.value(py);
+        if let Ok(msg) = value.str() {
+            let msg = format!("sandbox {name:?}: {}", msg.to_string_lossy());
+            let _: PyResult<()> = value.setattr("args", (msg,));
+        }
+        err
+    }
+
+    fn apply(
+        &mut self,
+        py: Python<'_>,
+        all_threads: bool,
+        audit_tag: Option<&str>,
+        assert_denied: Option<Vec<&str>>,
+    ) -> PyResult<()> {
+        let filter = self.to_filter(py)?;
+        if let Some(names) = assert_denied {
+            let syscalls = names
+                .into_iter()
+                .map(|name| {
+                    name.parse::<Sysno>()
+                        .map_err(|_| ExtraSafeError::new_err(format!("unknown syscall {name:?}")))
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            // The filter is probed before it is installed, so a filter that does not deny the
+            // syscalls is never installed, and the thread can still fork the probes.
+            probe::assert_denied(py, filter.clone(), &syscalls)?;
+        }
+        if let Some(audit_tag) = audit_tag {
+            write_audit_tag(audit_tag, all_threads);
+        }
+
+        // Compiling is slow, but the filter is cheap to gather and compare.
+        let key = format!("{all_threads} {filter:?}");
+        if let Some((compiled_key, bpf)) = &self.compiled {
+            if *compiled_key == key && filter.landlock_paths.is_none() {
+                load_bpf(py, bpf, all_threads)?;
+                self.last_applied_bpf = Some(bpf.clone());
+                return Ok(());
+            }
+        }
+        self.compile_count += 1;
+        self.last_applied_bpf = load_filter(py, filter, all_threads)?;
+        self.compiled = self.last_applied_bpf.clone().map(|bpf| (key, bpf));
+        Ok(())
+    }
+}
+
+/// The action for syscalls that the filter does not allow.
+fn parse_action(action: &str) -> PyResult<ScmpAction> {
+    Ok(match action {
+        "errno" => ScmpAction::Errno(Errno::PERM.raw_os_error()),
+        "kill_thread" => ScmpAction::KillThread,
+        "kill_process" => ScmpAction::KillProcess,
+        _ => {
+            return Err(ExtraSafeError::new_err(format!(
+                "unknown action {action:?}, expected \"errno\", \"kill_thread\", or \"kill_process\""
+            )));
+        },
+    })
+}
+
+#[pyfunction]
+pub(crate) fn sandbox_thread_initializer(context: Py<PySafetyContext>) -> ThreadInitializer {
+    ThreadInitializer { context }
+}
+
+#[pyclass]
+#[pyo3(name = "_ThreadInitializer")]
+#[derive(Debug)]
+pub(crate) struct ThreadInitializer {
+    context: Py<PySafetyContext>,
+}
+
+#[pymethods]
+impl ThreadInitializer {
+    fn __call__(&self, py: Python<'_>) -> PyResult<()> {
+        // Only borrow the context immutably, so worker threads don't conflict with each other.
+        let filter = self.context.borrow(py).to_filter(py)?;
+        let _: Option<Vec<u8>> = load_filter(py, filter, false)?;
+        Ok(())
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let context = self.context.as_ref(py).repr()?;
+        Ok(format!(
+            "<sandbox_thread_initializer {}>",
+            context.to_str()?
+        ))
+    }
+}
+
+fn syscall_numbers_rules(syscall_numbers: &[Sysno]) -> ExtraRules {
+    let mut rules = ExtraRules::new("SyscallNumbers");
+    rules.simple.extend(syscall_numbers);
+    rules
+}
+
+#[pyfunction]
+pub(crate) fn describe(py: Python<'_>, context: PyRef<'_, PySafetyContext>) -> PyResult<String> {
+    let mut s = "SafetyContext\n".to_owned();
+    for policy in &context.policies {
+        let policy = policy.borrow(py);
+        let inspect = policy.data.inspect();
+        let summary = PyDict::new(py);
+        inspect.summarize_to(summary)?;
+
+        s.push_str("  ");
+        s.push_str(inspect.name());
+        s.push('\n');
+        for (key, value) in summary {
+            let mut items = Vec::new();
+            for item in value.iter()? {
+                items.push(item?.str()?.to_str()?.to_owned());
+            }
+            if items.is_empty() && key.str()?.to_str()? != "flags" {
+                continue;
+            }
+            s.push_str("    ");
+            s.push_str(key.str()?.to_str()?);
+            s.push(':');
+            if !items.is_empty() {
+                s.push(' ');
+                s.push_str(&items.join(", "));
+            } else {
+                s.push_str(" -");
+            }
+            s.push('\n');
+        }
+        describe_syscalls(&mut s, &policy.to_filter()?);
+    }
+    if let Some(syscall_numbers) = &context.syscall_numbers {
+        s.push_str("  SyscallNumbers\n");
+        let mut filter = Filter::default();
+        let _: Result<(), _> = filter.enable(syscall_numbers_rules(syscall_numbers));
+        describe_syscalls(&mut s, &filter);
+    }
+    Ok(s)
+}
+
+/// Collect the syscalls in the seccomp entries of the kernel audit log, e.g. of
+/// [`PySafetyContext::apply_report_only()`].
+#[pyfunction]
+pub(crate) fn parse_seccomp_log(lines: &PyAny) -> PyResult<HashSet<String>> {
+    let mut syscalls = HashSet::new();
+    for line in lines.iter()? {
+        let line = line?;
+        let line: &str = line.extract()?;
+        if !line.contains("type=SECCOMP") && !line.contains("type=1326") {
+            continue;
+        }
+        let mut sysno = None;
+        let mut compat = false;
+        for field in line.split_ascii_whitespace() {
+            if let Some(value) = field.strip_prefix("syscall=") {
+                sysno = value.parse::<usize>().ok().and_then(Sysno::new);
+            } else if let Some(value) = field.strip_prefix("compat=") {
+                compat = value != "0";
+            }
+        }
+        // The numbers of compat syscalls are from a different table.
+        if let (Some(sysno), false) = (sysno, compat) {
+            let _: bool = syscalls.insert(sysno.name().to_owned());
+        }
+    }
+    Ok(syscalls)
+}
+
+fn describe_syscalls(s: &mut String, filter: &Filter) {
+    s.push_str("    syscalls:\n");
+    for (sysno, conditional) in filter.syscalls() {
+        s.push_str("      ");
+        s.push_str(sysno.name());
+        if conditional {
+            s.push_str(" (conditional)");
+        }
+        s.push('\n');
+    }
+}
+
+/// Compile and load a filter. Returns the loaded BPF program, if it could be recorded.
+pub(crate) fn load_filter(
+    py: Python<'_>,
+    filter: Filter,
+    all_threads: bool,
+) -> PyResult<Option<Vec<u8>>> {
+    CompiledFilter::new(filter, all_threads)?.load(py)
+}
+
+/// Load an already compiled BPF program, like [`ScmpFilterContext::load()`] would.
+fn load_bpf(py: Python<'_>, bpf: &[u8], all_threads: bool) -> PyResult<()> {
+    let target = CompiledFilter::target(all_threads);
+    if all_threads {
+        check_tsync()?;
+    }
+    let flags = match all_threads {
+        false => 0,
+        true => libc::SECCOMP_FILTER_FLAG_TSYNC,
+    };
+    let _: libc::c_long = load_bpf_with_flags(py, bpf, target, flags)?;
+    Ok(())
+}
+
+/// Load a BPF program on the current thread, and return the file descriptor of its listener.
+fn load_bpf_with_listener(py: Python<'_>, bpf: &[u8]) -> PyResult<OwnedFd> {
+    let flags = libc::SECCOMP_FILTER_FLAG_NEW_LISTENER;
+    let fd = load_bpf_with_flags(py, bpf, "current thread", flags)?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+/// Returns the result of the syscall `seccomp()`, e.g. the file descriptor of a new listener.
+fn load_bpf_with_flags(
+    py: Python<'_>,
+    bpf: &[u8],
+    target: &str,
+    flags: libc::c_ulong,
+) -> PyResult<libc::c_long> {
+    let mut filter = sock_filters(bpf);
+    let program = libc::sock_fprog {
+        len: filter.len() as libc::c_ushort,
+        filter: filter.as_mut_ptr(),
+    };
+    let result = thread::set_no_new_privs(true)
+        .map_err(io::Error::from)
+        .and_then(|()| {
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    flags,
+                    &program as *const libc::sock_fprog,
+                )
+            };
+            match result {
+                0.. => Ok(result),
+                _ => Err(io::Error::last_os_error()),
+            }
+        });
+    let (errno, msg) = match result {
+        // With TSYNC the kernel returns the ID of a thread that could not be synchronized, and
+        // installs the filter on no thread. libseccomp reports this as ESRCH, too.
+        Ok(tid) if tid != 0 && flags & libc::SECCOMP_FILTER_FLAG_TSYNC != 0 => (
+            Some(libc::ESRCH),
+            format!("thread {tid} could not be synchronized"),
+        ),
+        Ok(result) => return Ok(result),
+        Err(err) => (err.raw_os_error(), err.to_string()),
+    };
+    let hint = no_new_privs_hint(errno == Some(libc::EACCES));
+    let err = ApplyError::new_err(format!("could not apply to {target}: {msg}{hint}"));
+    let value = err.value(py);
+    value.setattr("errno", errno)?;
+    value.setattr("flags", flags)?;
+    value.setattr("bpf_len", filter.len())?;
+    Err(err)
+}
+
+/// A hint for the error message, if the kernel denied the filter because of a missing
+/// `PR_SET_NO_NEW_PRIVS`.
+fn no_new_privs_hint(denied: bool) -> &'static str {
+    match denied && thread::no_new_privs() == Ok(false) {
+        true => {
+            "; seccomp requires PR_SET_NO_NEW_PRIVS, call pyextrasafe.restrict_privileges() first"
+        },
+        false => "",
+    }
+}
+
+/// The size of a `struct sock_filter`, i.e. of a single BPF instruction.
+const SOCK_FILTER_SIZE: usize = 8;
+
+/// The [`libc`] value of a [`SeccompErrno`].
+fn raw_errno(errno: SeccompErrno) -> Option<libc::c_int> {
+    Some(match errno {
+        SeccompErrno::EACCES => libc::EACCES,
+        SeccompErrno::ECANCELED => libc::ECANCELED,
+        SeccompErrno::EDOM => libc::EDOM,
+        SeccompErrno::EEXIST => libc::EEXIST,
+        SeccompErrno::EFAULT => libc::EFAULT,
+        SeccompErrno::EINVAL => libc::EINVAL,
+        SeccompErrno::ENOENT => libc::ENOENT,
+        SeccompErrno::ENOMEM => libc::ENOMEM,
+        SeccompErrno::EOPNOTSUPP => libc::EOPNOTSUPP,
+        SeccompErrno::ERANGE => libc::ERANGE,
+        SeccompErrno::ESRCH => libc::ESRCH,
+        _ => return None,
+    })
+}
+
+/// A filter that is ready to be loaded.
+struct CompiledFilter {
+    ctx: ScmpFilterContext,
+    landlock: Option<Ruleset>,
+    bpf: Option<Vec<u8>>,
+    all_threads: bool,
+}
+
+impl CompiledFilter {
+    fn new(mut filter: Filter, all_threads: bool) -> PyResult<Self> {
+        if !filter.notified.is_empty() {
+            return Err(ExtraSafeError::new_err(format!(
+                "could not apply to {}: notified syscalls need a handler, use \
+                apply_with_handler() instead",
+                Self::target(all_threads),
+            )));
+        }
+        let landlock = match filter.landlock_paths.take() {
+            Some(_) if all_threads => {
+                return Err(ExtraSafeError::new_err(
+                    "could not apply to all threads: landlock can only restrict the current thread",
+                ));
+            },
+            Some(paths) => Some(Ruleset::new(&paths)?),
+            None => None,
+        };
+        let ctx = filter.compile(all_threads).map_err(|err| {
+            let target = Self::target(all_threads);
+            ExtraSafeError::new_err(format!("could not apply to {target}: {err}"))
+        })?;
+        // Exporting the filter needs syscalls that a previously installed filter might forbid.
+        let bpf = export_bpf(&ctx).ok();
+        Ok(Self {
+            ctx,
+            landlock,
+            bpf,
+            all_threads,
+        })
+    }
+
+    fn target(all_threads: bool) -> &'static str {
+        match all_threads {
+            false => "current thread",
+            true => "all threads",
+        }
+    }
+
+    /// Returns the loaded BPF program, if it could be recorded.
+    fn load(self, py: Python<'_>) -> PyResult<Option<Vec<u8>>> {
+        let target = Self::target(self.all_threads);
+        if self.all_threads {
+            check_tsync()?;
+        }
+        if let Some(landlock) = self.landlock {
+            landlock.restrict_self().map_err(|err| {
+                ApplyError::new_err(format!("could not apply landlock ruleset: {err}"))
+            })?;
+        }
+
+        if let Err(err) = self.ctx.load() {
+            let errno = err.errno();
+            let hint = no_new_privs_hint(errno == Some(SeccompErrno::EACCES));
+            let err = extrasafe::ExtraSafeError::from(err);
+            let err = ApplyError::new_err(format!("could not apply to {target}: {err}{hint}"));
+
+            let flags = match self.all_threads {
+                false => 0,
+                true => libc::SECCOMP_FILTER_FLAG_TSYNC,
+            };
+            let value = err.value(py);
+            value.setattr("errno", errno.and_then(raw_errno))?;
+            value.setattr("flags", flags)?;
+            value.setattr("bpf_len", self.bpf.map(|bpf| bpf.len() / SOCK_FILTER_SIZE))?;
+            return Err(err);
+        }
+        Ok(self.bpf)
+    }
+}
+
+#[pyfunction]
+#[pyo3(signature = (contexts, target="current_thread"))]
+pub(crate) fn apply_all(
+    py: Python<'_>,
+    contexts: Vec<Py<PySafetyContext>>,
+    target: &str,
+) -> PyResult<()> {
+    let all_threads = match target {
+        "current_thread" => false,
+        "all_threads" => true,
+        _ => {
+            return Err(ExtraSafeError::new_err(format!(
+                "unknown target {target:?}, expected \"current_thread\" or \"all_threads\""
+            )));
+        },
+    };
+
+    // Compile everything first, so that nothing is installed if any context is invalid.
+    let mut compiled = Vec::with_capacity(contexts.len());
+    for context in &contexts {
+        let filter = context.borrow(py).to_filter(py)?;
+        compiled.push(CompiledFilter::new(filter, all_threads)?);
+    }
+    for (context, compiled) in contexts.iter().zip(compiled) {
+        let bpf = compiled.load(py)?;
+        context.borrow_mut(py).last_applied_bpf = bpf;
+    }
+    Ok(())
+}
+
+#[pyfunction]
+pub(crate) fn apply_compute_only(py: Python<'_>) -> PyResult<()> {
+    let mut filter = Filter::default();
+    filter
+        .enable(SystemIO::nothing().allow_read().allow_write())
+        .and_then(|()| filter.enable(Time::nothing().allow_gettime()))
+        .map_err(|err| ExtraSafeError::new_err(format!("could not compile filter: {err}")))?;
+    let _: Option<Vec<u8>> = load_filter(py, filter, false)?;
+    Ok(())
+}
+
+#[pyfunction]
+pub(crate) fn reexec_sandboxed(
+    py: Python<'_>,
+    mut context: PyRefMut<'_, PySafetyContext>,
+    argv: Vec<OsString>,
+) -> PyResult<()> {
+    let argv = argv
+        .into_iter()
+        .map(|arg| CString::new(arg.into_vec()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ExtraSafeError::new_err("argv must not contain NUL characters"))?;
+    let Some(program) = argv.first() else {
+        return Err(ExtraSafeError::new_err("argv must not be empty"));
+    };
+    let allows_execve = context
+        .to_filter(py)?
+        .syscalls()
+        .iter()
+        .any(|&(sysno, _)| sysno == Sysno::execve);
+    if !allows_execve {
+        return Err(ExtraSafeError::new_err(
+            "the context does not allow execve, enable ForkAndExec",
+        ));
+    }
+
+    thread::set_no_new_privs(true)
+        .map_err(|err| ApplyError::new_err(format!("could not set PR_SET_NO_NEW_PRIVS: {err}")))?;
+    context.apply(py, false, None, None)?;
+
+    let mut args = argv.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+    args.push(null());
+    let _: libc::c_int = unsafe { libc::execv(program.as_ptr(), args.as_ptr()) };
+    let err = io::Error::last_os_error();
+    Err(ExtraSafeError::new_err(format!(
+        "could not execute {program:?}, but the filter is installed: {err}"
+    )))
+}
+
+/// The first line of a `sitecustomize.py` that was written by [`install_sitecustomize()`].
+const SITECUSTOMIZE_MARKER: &str = "# Generated by pyextrasafe.install_sitecustomize().";
+
+/// What the interpreter needs after the `sitecustomize` module ran: importing modules, running
+/// the main script, inspecting the standard streams, and writing to stdout and stderr.
+const STARTUP_SAFE_SPEC: &str = r#"{
+    "SystemIO": [
+        "allow_close",
+        "allow_ioctl",
+        "allow_metadata",
+        "allow_open_readonly",
+        "allow_read",
+        "allow_scandir",
+        "allow_stderr",
+        "allow_stdout"
+    ],
+    "Time": ["allow_gettime"]
+}"#;
+
+#[pyfunction]
+pub(crate) fn install_sitecustomize(
+    py: Python<'_>,
+    context: PyRef<'_, PySafetyContext>,
+    directory: PathBuf,
+) -> PyResult<PathBuf> {
+    let spec = PyString::new(py, &context.to_spec(py)?).repr()?;
+    let path = directory.join("sitecustomize.py");
+    let err = |err: io::Error| {
+        ExtraSafeError::new_err(format!("could not write {}: {err}", path.display()))
+    };
+
+    match read_to_string(&path) {
+        Ok(content) if !content.starts_with(SITECUSTOMIZE_MARKER) => {
+            return Err(ExtraSafeError::new_err(format!(
+                "refusing to overwrite {}, which was not written by pyextrasafe",
+                path.display(),
+            )));
+        },
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(err(e)),
+        _ => {},
+    }
+    let content = format!(
+        "{SITECUSTOMIZE_MARKER}\n\
+        # Applies the sandbox at interpreter startup, before the main module is run.\n\
+        import pyextrasafe\n\
+        \n\
+        pyextrasafe.SafetyContext.from_json({spec}).apply_to_all_threads()\n",
+    );
+    write(&path, content).map_err(err)?;
+    Ok(path)
+}
+
+/// Write a marker to the kernel log, so the audit log entries of the filter can be attributed.
+/// This is best-effort: most processes are not allowed to write to `/dev/kmsg`.
+fn write_audit_tag(audit_tag: &str, all_threads: bool) {
+    let target = match all_threads {
+        false => format!("thread {}", gettid().as_raw_nonzero()),
+        true => "all threads".to_owned(),
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let msg = format!(
+        "pyextrasafe[{}]: applying policy {audit_tag:?} to {target} at {timestamp}\n",
+        getpid().as_raw_nonzero(),
+    );
+    if let Ok(mut kmsg) = OpenOptions::new().write(true).open("/dev/kmsg") {
+        let _: io::Result<()> = kmsg.write_all(msg.as_bytes());
+    }
+}
+
+/// Applying a filter to all threads installs the calling thread's filters on all other threads,
+/// too. If the calling thread was sandboxed on its own before, then the other threads would
+/// become more restricted than intended.
+fn check_tsync() -> PyResult<()> {
+    let Some(own) = seccomp_filters(Path::new("/proc/thread-self/status")) else {
+        return Ok(());
+    };
+    let Ok(tasks) = read_dir("/proc/self/task") else {
+        return Ok(());
+    };
+    for task in tasks.flatten() {
+        let Some(other) = seccomp_filters(&task.path().join("status")) else {
+            continue;
+        };
+        if other < own {
+            return Err(ApplyError::new_err(format!(
+                "could not apply to all threads: the calling thread has {own} seccomp filters, \
+                but thread {} has only {other}, so it would become more restricted than intended",
+                task.file_name().to_string_lossy(),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The number of seccomp filters of a thread, if the kernel tells us (Linux 5.9+).
+fn seccomp_filters(status: &Path) -> Option<usize> {
+    let status = read_to_string(status).ok()?;
+    let value = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Seccomp_filters:"))?;
+    value.trim().parse().ok()
 }
 
 #[pymethods]
 impl PySafetyContext {
     #[new]
-    pub(crate) fn new() -> Self {
-        Self(Vec::new())
+    #[pyo3(signature = (
+        *,
+        name=None,
+        strict_duplicates=false,
+        on_violation="errno",
+        include_basics=true,
+    ))]
+    fn py_new(
+        name: Option<String>,
+        strict_duplicates: bool,
+        on_violation: &str,
+        include_basics: bool,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            name,
+            strict_duplicates,
+            include_basics,
+            default_action: Some(parse_action(on_violation)?),
+            ..Self::new()
+        })
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (nums, default_action="kill_process"))]
+    fn from_syscall_numbers(nums: &PyAny, default_action: &str) -> PyResult<Self> {
+        let default_action = parse_action(default_action)?;
+
+        let mut syscall_numbers = Vec::new();
+        for num in nums.iter()? {
+            let num: i64 = num?.extract()?;
+            let sysno = usize::try_from(num)
+                .ok()
+                .and_then(Sysno::new)
+                .ok_or_else(|| {
+                    ExtraSafeError::new_err(format!("{num} is not a valid syscall number"))
+                })?;
+            syscall_numbers.push(sysno);
+        }
+        syscall_numbers.sort_unstable();
+        syscall_numbers.dedup();
+
+        Ok(Self {
+            syscall_numbers: Some(syscall_numbers),
+            default_action: Some(default_action),
+            ..Self::new()
+        })
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (summary, default_action="kill_process"))]
+    fn from_strace(summary: &str, default_action: &str) -> PyResult<(Self, Vec<String>)> {
+        let default_action = parse_action(default_action)?;
+
+        let mut syscall_numbers = Vec::new();
+        let mut skipped = Vec::new();
+        for line in summary.lines() {
+            let line = line.trim();
+            // The header, the separators, and the footer of the table.
+            if line.is_empty() || line.starts_with('%') || line.starts_with('-') {
+                continue;
+            }
+            let mut columns = line.split_ascii_whitespace();
+            let is_row = columns
+                .next()
+                .is_some_and(|percent| percent.parse::<f64>().is_ok());
+            match columns.last() {
+                Some("total") if is_row => {},
+                Some(name) if is_row => match name.parse::<Sysno>() {
+                    Ok(sysno) => syscall_numbers.push(sysno),
+                    Err(_) => skipped.push(line.to_owned()),
+                },
+                _ => skipped.push(line.to_owned()),
+            }
+        }
+        syscall_numbers.sort_unstable();
+        syscall_numbers.dedup();
+
+        let ctx = Self {
+            syscall_numbers: Some(syscall_numbers),
+            default_action: Some(default_action),
+            ..Self::new()
+        };
+        Ok((ctx, skipped))
     }
 
-    #[pyo3(signature = (*policies))]
-    fn enable(
+    #[staticmethod]
+    #[pyo3(signature = (var="PYEXTRASAFE_POLICY", *, required=false))]
+    fn from_env(py: Python<'_>, var: &str, required: bool) -> PyResult<Self> {
+        let spec = match std::env::var(var) {
+            Ok(spec) => spec,
+            Err(VarError::NotPresent) if !required => return Ok(Self::new()),
+            Err(err) => {
+                return Err(ExtraSafeError::new_err(format!(
+                    "could not read environment variable {var:?}: {err}"
+                )));
+            },
+        };
+        Self::from_spec(py, &spec, &format!("environment variable {var:?}"))
+    }
+
+    #[staticmethod]
+    fn from_json(py: Python<'_>, spec: &str) -> PyResult<Self> {
+        Self::from_spec(py, spec, "the policy")
+    }
+
+    #[staticmethod]
+    fn startup_safe(py: Python<'_>) -> PyResult<Self> {
+        Self::from_spec(py, STARTUP_SAFE_SPEC, "startup_safe()")
+    }
+
+    #[pyo3(signature = (*policies, **flags))]
+    fn enable<'py>(
+        mut ctx: PyRefMut<'py, Self>,
+        policies: &PyTuple,
+        flags: Option<&PyDict>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let py = ctx.py();
+        ctx.ensure_mutable(py)?;
+        let source = "the arguments of enable()";
+        let mut policies = match flags {
+            Some(flags) => {
+                let name = match policies.as_slice() {
+                    [name] => name.extract::<&str>().ok(),
+                    _ => None,
+                };
+                let Some(name) = name else {
+                    return Err(ExtraSafeError::new_err(
+                        "keyword flags need the name of a rule set as the only argument",
+                    ));
+                };
+                let mut names = Vec::new();
+                for (flag, value) in flags {
+                    if value.is_true()? {
+                        let flag = flag.extract::<&str>()?;
+                        match flag.starts_with("allow_") {
+                            true => names.push(flag.to_owned()),
+                            false => names.push(format!("allow_{flag}")),
+                        }
+                    }
+                }
+                let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+                vec![Self::named_policy(py, name, &names, source)?]
+            },
+            None => policies
+                .iter()
+                .map(|policy| match policy.extract::<&str>() {
+                    Ok(name) => Self::named_policy(py, name, &[], source),
+                    Err(_) => policy.extract(),
+                })
+                .collect::<PyResult<Vec<_>>>()?,
+        };
+        if ctx.strict_duplicates {
+            let mut names = ctx
+                .policies
+                .iter()
+                .map(|policy| policy.borrow(py).data.inspect().name())
+                .collect::<Vec<_>>();
+            for policy in &policies {
+                let name = policy.borrow(py).data.inspect().name();
+                if names.contains(&name) {
+                    return Err(ExtraSafeError::new_err(format!(
+                        "a {name} rule set is already enabled, merge them with `|` instead"
+                    )));
+                }
+                names.push(name);
+            }
+        }
+        ctx.policies.append(&mut policies);
+        Ok(ctx)
+    }
+
+    fn deduplicate(mut ctx: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        let py = ctx.py();
+        ctx.ensure_mutable(py)?;
+        let mut deduplicated: Vec<Py<PyRuleSet>> = Vec::new();
+        for policy in ctx.policies.drain(..) {
+            let name = policy.borrow(py).data.inspect().name();
+            let Some(known) = deduplicated
+                .iter_mut()
+                .find(|known| known.borrow(py).data.inspect().name() == name)
+            else {
+                deduplicated.push(policy);
+                continue;
+            };
+            // The enabled rule sets belong to the caller, so they are not modified.
+            let Some(merged) = known.borrow(py).merged(py, &policy.borrow(py))? else {
+                unreachable!("Impossible content");
+            };
+            *known = merged.extract(py)?;
+        }
+        ctx.policies = deduplicated;
+        Ok(ctx)
+    }
+
+    fn allow_file_read(mut ctx: PyRefMut<'_, Self>, fileno: RawFd) -> PyResult<PyRefMut<'_, Self>> {
+        ctx.ensure_mutable(ctx.py())?;
+        insert_sorted_fileno(&mut ctx.read_fds, fileno)?;
+        Ok(ctx)
+    }
+
+    fn allow_file_write(
         mut ctx: PyRefMut<'_, Self>,
-        mut policies: Vec<Py<PyRuleSet>>,
+        fileno: RawFd,
     ) -> PyResult<PyRefMut<'_, Self>> {
-        ctx.0.append(&mut policies);
+        ctx.ensure_mutable(ctx.py())?;
+        insert_sorted_fileno(&mut ctx.write_fds, fileno)?;
+        Ok(ctx)
+    }
+
+    fn forbid<'py>(
+        mut ctx: PyRefMut<'py, Self>,
+        names: Vec<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        ctx.ensure_mutable(ctx.py())?;
+        for name in names {
+            let sysno: Sysno = name
+                .parse()
+                .map_err(|_| ExtraSafeError::new_err(format!("unknown syscall {name:?}")))?;
+            if let Err(pos) = ctx.forbidden.binary_search(&sysno) {
+                ctx.forbidden.insert(pos, sysno);
+            }
+        }
+        Ok(ctx)
+    }
+
+    fn notify<'py>(
+        mut ctx: PyRefMut<'py, Self>,
+        names: Vec<&str>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        ctx.ensure_mutable(ctx.py())?;
+        for name in names {
+            let sysno: Sysno = name
+                .parse()
+                .map_err(|_| ExtraSafeError::new_err(format!("unknown syscall {name:?}")))?;
+            if let Err(pos) = ctx.notified.binary_search(&sysno) {
+                ctx.notified.insert(pos, sysno);
+            }
+        }
+        Ok(ctx)
+    }
+
+    fn freeze(mut ctx: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        let py = ctx.py();
+        // The enabled rule sets belong to the caller, so they are replaced by frozen copies.
+        // Modifying a frozen rule set returns a copy, so the copies stay unchanged.
+        for policy in &mut ctx.policies {
+            let mut copy = policy.borrow(py).clone();
+            copy.frozen = true;
+            *policy = copy.into_instance(py)?.extract(py)?;
+        }
+        ctx.frozen = true;
         Ok(ctx)
     }
 
-    fn apply_to_current_thread(&mut self, py: Python<'_>) -> PyResult<()> {
-        self.to_context(py)?
-            .apply_to_current_thread()
+    #[pyo3(signature = (*, audit_tag=None, assert_denied=None))]
+    fn apply_to_current_thread(
+        &mut self,
+        py: Python<'_>,
+        audit_tag: Option<&str>,
+        assert_denied: Option<Vec<&str>>,
+    ) -> PyResult<()> {
+        self.apply(py, false, audit_tag, assert_denied)
+            .map_err(|err| self.named_err(py, err))
+    }
+
+    #[pyo3(signature = (*, audit_tag=None, assert_denied=None))]
+    fn apply_to_all_threads(
+        &mut self,
+        py: Python<'_>,
+        audit_tag: Option<&str>,
+        assert_denied: Option<Vec<&str>>,
+    ) -> PyResult<()> {
+        self.apply(py, true, audit_tag, assert_denied)
+            .map_err(|err| self.named_err(py, err))
+    }
+
+    #[pyo3(signature = (*, all_threads=false))]
+    fn apply_report_only(&mut self, py: Python<'_>, all_threads: bool) -> PyResult<()> {
+        let mut filter = self.to_filter(py)?;
+        if filter.landlock_paths.is_some() {
+            return Err(ExtraSafeError::new_err(
+                "could not apply in report-only mode: landlock cannot only report violations",
+            ));
+        }
+        filter.default_action = Some(ScmpAction::Log);
+        self.last_applied_bpf = load_filter(py, filter, all_threads)?;
+        Ok(())
+    }
+
+    #[pyo3(signature = (handler, *, timeout=1.0))]
+    fn apply_with_handler(
+        &mut self,
+        py: Python<'_>,
+        handler: Py<PyAny>,
+        timeout: f64,
+    ) -> PyResult<()> {
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .map_err(|_| ExtraSafeError::new_err("`timeout` must be a non-negative number"))?;
+        if !handler.as_ref(py).is_callable() {
+            return Err(PyTypeError::new_err("`handler` must be callable"));
+        }
+        let filter = self.to_filter(py)?;
+        let err = |msg: &str| {
+            self.named_err(
+                py,
+                ExtraSafeError::new_err(format!("could not apply with handler: {msg}")),
+            )
+        };
+        if filter.notified.is_empty() {
+            return Err(err("no syscalls are notified, call notify() first"));
+        } else if filter.landlock_paths.is_some() {
+            return Err(err("landlock is not supported"));
+        }
+        let ctx = filter.compile(false).map_err(|e| err(&e.to_string()))?;
+        let bpf = export_bpf(&ctx).map_err(|e| err(&format!("could not export filter: {e}")))?;
+
+        // The supervisor must be started before the filter is loaded, or the filter restricts it.
+        let supervisor = spawn_supervisor(handler, timeout)
+            .map_err(|e| err(&format!("could not start supervisor: {e}")))?;
+        let fd = load_bpf_with_listener(py, &bpf).map_err(|err| self.named_err(py, err))?;
+        self.last_applied_bpf = Some(bpf);
+        // The supervisor waits for the listener, so it cannot be gone yet.
+        let _: Result<(), _> = supervisor.send(fd);
+        Ok(())
+    }
+
+    #[pyo3(signature = (tids, *, timeout=1.0))]
+    fn apply_to_threads(
+        &mut self,
+        py: Python<'_>,
+        mut tids: Vec<libc::pid_t>,
+        timeout: f64,
+    ) -> PyResult<()> {
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .map_err(|_| ExtraSafeError::new_err("`timeout` must be a non-negative number"))?;
+        tids.sort_unstable();
+        tids.dedup();
+
+        let filter = self.to_filter(py)?;
+        if filter.landlock_paths.is_some() {
+            return Err(ExtraSafeError::new_err(
+                "could not apply to threads: landlock can only restrict the current thread",
+            ));
+        } else if !filter.notified.is_empty() {
+            return Err(ExtraSafeError::new_err(
+                "could not apply to threads: notified syscalls need a handler",
+            ));
+        }
+        let ctx = filter
+            .compile(false)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not apply to threads: {err}")))?;
+        let bpf = export_bpf(&ctx).map_err(|err| {
+            ApplyError::new_err(format!(
+                "could not apply to threads: could not export filter: {err}"
+            ))
+        })?;
+
+        let errors = py
+            .allow_threads(|| install_on_threads(&bpf, &tids, timeout))
+            .map_err(|err| ApplyError::new_err(format!("could not apply to threads: {err}")))?;
+        if !errors.is_empty() {
+            let errors = errors
+                .into_iter()
+                .map(|(tid, err)| match err {
+                    ThreadError::Os(err) => format!("{tid} ({err})"),
+                    ThreadError::TimedOut => format!("{tid} (timed out)"),
+                })
+                .collect::<Vec<_>>();
+            return Err(ApplyError::new_err(format!(
+                "could not apply to threads {}",
+                errors.join(", "),
+            )));
+        }
+        self.last_applied_bpf = Some(bpf);
+        Ok(())
+    }
+
+    #[getter]
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn rule_sets(&self, py: Python<'_>) -> PyResult<Vec<Py<PyAny>>> {
+        self.policies
+            .iter()
+            .map(|policy| policy.borrow(py).to_py_copy(py))
+            .collect()
+    }
+
+    fn summary(&self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        let summary = PyDict::new(py);
+        for policy in &self.policies {
+            let policy = policy.borrow(py);
+            let policy = policy.data.inspect();
+            let key = policy.name().to_lowercase();
+            let entry = match summary.get_item(&key) {
+                Some(entry) => entry.downcast()?,
+                None => {
+                    let entry = PyDict::new(py);
+                    summary.set_item(key, entry)?;
+                    entry
+                },
+            };
+            policy.summarize_to(entry)?;
+        }
+        Ok(summary.into())
+    }
+
+    fn effective_flags(&self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        let policies: Vec<_> = self
+            .policies
+            .iter()
+            .map(|policy| policy.borrow(py))
+            .collect();
+        let mut merged: Vec<(&dyn InspectPolicy, u32)> = Vec::new();
+        if self.syscall_numbers.is_none() && self.include_basics {
+            merged.push((DataRuleSet::basics(), 0));
+        }
+        let mut read_fds = self.read_fds.clone();
+        let mut write_fds = self.write_fds.clone();
+        for policy in &policies {
+            if let Some((rd, wr)) = policy.data.file_descriptors() {
+                for &fileno in rd {
+                    insert_sorted_fileno(&mut read_fds, fileno)?;
+                }
+                for &fileno in wr {
+                    insert_sorted_fileno(&mut write_fds, fileno)?;
+                }
+            }
+            let policy = policy.data.inspect();
+            match merged
+                .iter_mut()
+                .find(|(known, _)| known.name() == policy.name())
+            {
+                Some((_, bits)) => *bits |= policy.flag_bits(),
+                None => merged.push((policy, policy.flag_bits())),
+            }
+        }
+
+        let result = PyDict::new(py);
+        for (policy, bits) in merged {
+            result.set_item(policy.name().to_lowercase(), policy.flag_names_of(bits))?;
+        }
+        if !read_fds.is_empty() || !write_fds.is_empty() {
+            let _: &PyAny = result.call_method1("setdefault", ("systemio", PyList::empty(py)))?;
+            result.set_item("read_fds", read_fds)?;
+            result.set_item("write_fds", write_fds)?;
+        }
+        Ok(result.into())
+    }
+
+    fn kernel_validate(&self, py: Python<'_>) -> PyResult<()> {
+        let bpf = self.compile_bpf(py)?;
+        let errno = py
+            .allow_threads(|| probe::kernel_validate(&bpf))
             .map_err(|err| {
-                ExtraSafeError::new_err(format!("could not apply to current thread: {err}"))
-            })
+                ExtraSafeError::new_err(format!("could not validate the filter: {err}"))
+            })?;
+        if let Some(errno) = errno {
+            let err = io::Error::from_raw_os_error(errno);
+            let err = ApplyError::new_err(format!("the kernel rejected the filter: {err}"));
+            let value = err.value(py);
+            value.setattr("errno", errno)?;
+            value.setattr("flags", 0)?;
+            value.setattr("bpf_len", bpf.len() / SOCK_FILTER_SIZE)?;
+            return Err(err);
+        }
+        Ok(())
     }
 
-    fn apply_to_all_threads(&mut self, py: Python<'_>) -> PyResult<()> {
-        self.to_context(py)?.apply_to_all_threads().map_err(|err| {
-            ExtraSafeError::new_err(format!("could not apply to all threads: {err}"))
-        })
+    #[pyo3(signature = (iterations=100_000))]
+    fn benchmark_syscall_overhead(&self, py: Python<'_>, iterations: u32) -> PyResult<Py<PyDict>> {
+        // Only the seccomp filter adds to the cost of every syscall.
+        const CANDIDATES: [Sysno; 3] = [Sysno::getpid, Sysno::gettid, Sysno::clock_gettime];
+
+        if iterations == 0 {
+            return Err(ExtraSafeError::new_err("`iterations` must be positive"));
+        }
+        let mut filter = self.to_filter(py)?;
+        filter.landlock_paths = None;
+        if !filter.without_basics {
+            filter.without_basics = true;
+            filter.enable(BasicCapabilities).map_err(|err| {
+                ExtraSafeError::new_err(format!("could not compile filter: {err}"))
+            })?;
+        }
+        let allowed = filter.syscalls();
+        let Some(sysno) = CANDIDATES
+            .into_iter()
+            .find(|sysno| allowed.contains(&(*sysno, false)))
+        else {
+            return Err(ExtraSafeError::new_err(
+                "could not benchmark the filter: it allows none of getpid, gettid, and clock_gettime \
+                unconditionally",
+            ));
+        };
+
+        let ctx = filter
+            .compile(false)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not compile filter: {err}")))?;
+        let bpf = export_bpf(&ctx)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not export filter: {err}")))?;
+        let (baseline, sandboxed) = py
+            .allow_threads(|| probe::benchmark(&bpf, sysno, iterations))
+            .map_err(|err| {
+                ExtraSafeError::new_err(format!("could not benchmark the filter: {err}"))
+            })?;
+
+        let result = PyDict::new(py);
+        result.set_item("syscall", sysno.name())?;
+        result.set_item("baseline_ns", baseline)?;
+        result.set_item("sandboxed_ns", sandboxed)?;
+        Ok(result.into())
+    }
+
+    fn is_subset_of(&self, py: Python<'_>, other: PyRef<'_, Self>) -> PyResult<bool> {
+        self.to_filter(py)?
+            .covered_by(other.to_filter(py)?)
+            .map_err(|err| ExtraSafeError::new_err(format!("could not compare filters: {err}")))
+    }
+
+    fn added_syscalls(
+        &self,
+        py: Python<'_>,
+        previous: PyRef<'_, Self>,
+    ) -> PyResult<Vec<&'static str>> {
+        let previous = previous.allowed_syscalls(py)?;
+        Ok(self
+            .allowed_syscalls(py)?
+            .into_iter()
+            .filter(|sysno| !previous.contains(sysno))
+            .map(|sysno| sysno.name())
+            .collect())
+    }
+
+    fn removed_syscalls(
+        &self,
+        py: Python<'_>,
+        previous: PyRef<'_, Self>,
+    ) -> PyResult<Vec<&'static str>> {
+        let current = self.allowed_syscalls(py)?;
+        Ok(previous
+            .allowed_syscalls(py)?
+            .into_iter()
+            .filter(|sysno| !current.contains(sysno))
+            .map(|sysno| sysno.name())
+            .collect())
+    }
+
+    fn filter_digest(&self, py: Python<'_>) -> PyResult<String> {
+        let bpf = PyBytes::new(py, &self.compile_bpf(py)?);
+        let digest = py.import("hashlib")?.call_method1("sha256", (bpf,))?;
+        digest.call_method0("hexdigest")?.extract()
+    }
+
+    #[pyo3(signature = (var_name="filter"))]
+    fn to_c_array(&self, py: Python<'_>, var_name: &str) -> PyResult<String> {
+        let is_identifier = var_name
+            .chars()
+            .enumerate()
+            .all(|(idx, c)| c == '_' || c.is_ascii_alphabetic() || (idx > 0 && c.is_ascii_digit()));
+        if var_name.is_empty() || !is_identifier {
+            return Err(ExtraSafeError::new_err(format!(
+                "{var_name:?} is not a valid C identifier"
+            )));
+        }
+
+        let bpf = self.compile_bpf(py)?;
+        let mut s = format!("static struct sock_filter {var_name}[] = {{\n");
+        for insn in sock_filters(&bpf) {
+            s.push_str(&format!(
+                "\t{{ 0x{:02x}, {}, {}, 0x{:08x} }},\n",
+                insn.code, insn.jt, insn.jf, insn.k,
+            ));
+        }
+        s.push_str("};\n");
+        Ok(s)
+    }
+
+    #[getter(_compile_count)]
+    fn compile_count(&self) -> usize {
+        self.compile_count
+    }
+
+    fn last_applied_bpf(&self, py: Python<'_>) -> Option<Py<PyBytes>> {
+        let bpf = self.last_applied_bpf.as_deref()?;
+        Some(PyBytes::new(py, bpf).into())
     }
 
     fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
-        let mut s = "<SafetyContext [".to_owned();
-        for (idx, policy) in self.0.iter().enumerate() {
+        let mut s = "<SafetyContext ".to_owned();
+        if let Some(name) = &self.name {
+            s.push_str(&format!("{name:?} "));
+        }
+        s.push('[');
+        for (idx, policy) in self.policies.iter().enumerate() {
             if idx > 0 {
                 s.push_str(", ");
             }
@@ -72,11 +1436,11 @@ impl PySafetyContext {
     }
 
     fn __len__(&self) -> usize {
-        self.0.len()
+        self.policies.len()
     }
 
     fn __bool__(&self) -> bool {
-        !self.0.is_empty()
+        !self.policies.is_empty()
     }
 }
 
@@ -95,7 +1459,7 @@ impl SafetyContextIter {
     }
 
     fn __next__(&mut self, py: Python<'_>) -> Option<Py<PyRuleSet>> {
-        let result = self.ctx.borrow(py).0.get(self.idx)?.clone();
+        let result = self.ctx.borrow(py).policies.get(self.idx)?.clone();
         self.idx += 1;
         Some(result)
     }
@@ -105,6 +1469,6 @@ impl SafetyContextIter {
     }
 
     fn __len__(&self, py: Python<'_>) -> usize {
-        self.ctx.borrow(py).0.len().saturating_sub(self.idx)
+        self.ctx.borrow(py).policies.len().saturating_sub(self.idx)
     }
 }
@@ -0,0 +1,26 @@
+import subprocess
+import sys
+import textwrap
+
+import pytest
+
+
+@pytest.fixture
+def run_python():
+    """
+    Run Python code in a new interpreter.
+
+    Seccomp filters cannot be removed, so tests that apply a filter to the whole process, or that
+    should crash, must not run in the test process itself.
+    """
+
+    def run(code, *args, **kwargs):
+        return subprocess.run(
+            [sys.executable, "-c", textwrap.dedent(code), *args],
+            capture_output=True,
+            text=True,
+            timeout=60,
+            **kwargs,
+        )
+
+    return run
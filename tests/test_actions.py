@@ -0,0 +1,30 @@
+import signal
+
+import pytest
+
+import pyextrasafe
+
+
+def test_kill_is_ambiguous():
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="unknown action"):
+        pyextrasafe.SafetyContext(on_violation="kill")
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="unknown action"):
+        pyextrasafe.SafetyContext.from_syscall_numbers([], default_action="kill")
+
+
+def test_syscall_numbers_kill_the_process_by_default(run_python):
+    result = run_python(
+        """
+        import threading
+        import pyextrasafe
+
+        # The new thread is killed, and with it the whole process.
+        ctx = pyextrasafe.SafetyContext.from_syscall_numbers([])
+        thread = threading.Thread(target=ctx.apply_to_current_thread)
+        thread.start()
+        thread.join()
+        print("still running")
+        """
+    )
+    assert result.returncode == -signal.SIGSYS
+    assert result.stdout == ""
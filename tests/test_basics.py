@@ -0,0 +1,16 @@
+import pyextrasafe
+
+
+def compute():
+    return sorted(str(i) for i in range(1_000))[:3]
+
+
+def test_basics_are_included_by_default():
+    ctx = pyextrasafe.SafetyContext()
+    assert pyextrasafe.run_isolated(ctx, compute) == ["0", "1", "10"]
+
+
+def test_basics_can_be_included_explicitly():
+    ctx = pyextrasafe.SafetyContext(include_basics=False)
+    ctx.enable(pyextrasafe.BasicCapabilities())
+    assert pyextrasafe.run_isolated(ctx, compute) == ["0", "1", "10"]
@@ -0,0 +1,80 @@
+import platform
+
+import pytest
+
+# The numbers of the syscall seccomp, which differ between the architectures.
+SYS_SECCOMP = {"x86_64": 317, "aarch64": 277}
+
+
+def test_filter_is_compiled_once(run_python):
+    result = run_python(
+        """
+        import threading
+        import pyextrasafe
+
+        ctx = pyextrasafe.SafetyContext()
+
+        def apply_in_new_thread():
+            # Only the new thread is sandboxed, not the calling thread.
+            thread = threading.Thread(target=ctx.apply_to_current_thread)
+            thread.start()
+            thread.join()
+
+        for _ in range(3):
+            apply_in_new_thread()
+        print(ctx._compile_count)
+
+        ctx.enable(pyextrasafe.Time().allow_gettime())
+        apply_in_new_thread()
+        apply_in_new_thread()
+        print(ctx._compile_count)
+        """
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.split() == ["1", "2"]
+
+
+
+@pytest.mark.skipif(
+    platform.machine() not in SYS_SECCOMP, reason="unknown architecture"
+)
+def test_cached_filter_fails_if_a_thread_cannot_be_synced(run_python):
+    result = run_python(
+        f"""
+        import errno
+        import threading
+        import pyextrasafe
+
+        # The worker must be able to install a filter of its own after the first one.
+        ctx = pyextrasafe.SafetyContext.from_syscall_numbers(
+            [{SYS_SECCOMP[platform.machine()]}], default_action="errno"
+        ).enable(
+            pyextrasafe.ProcessControl().allow_prctl_option(38),  # PR_SET_NO_NEW_PRIVS
+            pyextrasafe.SystemIO().allow_stdout(),
+        )
+        filtered = threading.Event()
+        done = threading.Event()
+
+        def worker():
+            filtered.wait()
+            pyextrasafe.SafetyContext().apply_to_current_thread()
+            done.set()
+
+        thread = threading.Thread(target=worker)
+        thread.start()
+        ctx.apply_to_all_threads()
+        filtered.set()
+        done.wait()
+
+        # The worker has a filter that the calling thread doesn't have, so TSYNC fails.
+        try:
+            ctx.apply_to_all_threads()
+        except pyextrasafe.ApplyError as err:
+            print(ctx._compile_count, err.errno == errno.ESRCH, thread.native_id, err)
+        thread.join()
+        """
+    )
+    assert result.returncode == 0, result.stderr
+    compile_count, is_esrch, tid, msg = result.stdout.split(maxsplit=3)
+    assert (compile_count, is_esrch) == ("1", "True")
+    assert f"thread {tid} could not be synchronized" in msg
@@ -0,0 +1,66 @@
+import pyextrasafe
+
+
+def test_compute_workload_runs(run_python):
+    result = run_python(
+        """
+        import fractions
+        import json
+        import time
+        import pyextrasafe
+
+        pyextrasafe.apply_compute_only()
+
+        started = time.monotonic()
+        values = sorted(fractions.Fraction(i, 7) ** 2 for i in range(10_000))
+        data = {str(i): [i] * 100 for i in range(1_000)}
+        print(json.dumps([str(sum(values)), len(data)]))
+        assert time.monotonic() >= started
+        """
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.strip() == '["47611905000/7", 1000]'
+
+
+def test_open_is_denied(run_python):
+    result = run_python(
+        """
+        import sys
+        import pyextrasafe
+
+        pyextrasafe.apply_compute_only()
+        try:
+            open(sys.executable, "rb")
+        except PermissionError:
+            print("denied")
+        """
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.strip() == "denied"
+
+
+def test_socket_is_denied(run_python):
+    result = run_python(
+        """
+        import socket
+        import pyextrasafe
+
+        pyextrasafe.apply_compute_only()
+        try:
+            socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+        except PermissionError:
+            print("denied")
+        """
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.strip() == "denied"
+
+
+def test_existing_file_descriptors_are_usable():
+    def compute():
+        pyextrasafe.apply_compute_only()
+        return sum(i * i for i in range(1_000))
+
+    # The result is written to a pipe that was opened before the filter was applied.
+    ctx = pyextrasafe.SafetyContext()
+    assert pyextrasafe.run_isolated(ctx, compute) == 332833500
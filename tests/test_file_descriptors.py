@@ -0,0 +1,61 @@
+import os
+
+import pytest
+
+import pyextrasafe
+
+
+def test_file_descriptor_is_registered_once():
+    read_fd, write_fd = os.pipe()
+    try:
+        io = pyextrasafe.SystemIO().allow_file_read(read_fd)
+        both = pyextrasafe.SafetyContext().enable(io).allow_file_read(read_fd)
+        only_io = pyextrasafe.SafetyContext().enable(io)
+        only_ctx = pyextrasafe.SafetyContext().allow_file_read(read_fd)
+        assert both.filter_digest() == only_io.filter_digest()
+        assert both.filter_digest() == only_ctx.filter_digest()
+    finally:
+        os.close(read_fd)
+        os.close(write_fd)
+
+
+def test_file_descriptor_registered_twice_is_readable():
+    read_fd, write_fd = os.pipe()
+    try:
+        os.write(write_fd, b"data")
+        io = pyextrasafe.SystemIO().allow_file_read(read_fd)
+        ctx = pyextrasafe.SafetyContext().enable(io).allow_file_read(read_fd)
+        assert pyextrasafe.run_isolated(ctx, os.read, read_fd, 4) == b"data"
+    finally:
+        os.close(read_fd)
+        os.close(write_fd)
+
+
+@pytest.mark.parametrize(
+    "broad, scoped",
+    [
+        (lambda io: io.allow_rename(), lambda io: io.allow_rename_flags(2)),
+        (lambda io: io.allow_read().allow_write(), lambda io: io.allow_low_fds(3)),
+        (lambda io: io.allow_ioctl(), lambda io: io.allow_fcntl_cmd(3)),
+        (
+            lambda io: io.allow_open().yes_really(),
+            lambda io: io.allow_open_flags(os.O_CREAT),
+        ),
+    ],
+)
+def test_argument_rule_is_skipped_if_all_values_are_allowed(broad, scoped):
+    only_broad = pyextrasafe.SafetyContext().enable(broad(pyextrasafe.SystemIO()))
+    both = pyextrasafe.SafetyContext().enable(scoped(broad(pyextrasafe.SystemIO())))
+    assert both.filter_digest() == only_broad.filter_digest()
+
+
+def test_effective_flags_list_all_file_descriptors():
+    io = pyextrasafe.SystemIO().allow_stdout().allow_file_read(5)
+    ctx = pyextrasafe.SafetyContext().enable(io).allow_file_read(3).allow_file_write(4)
+    assert ctx.effective_flags() == {
+        "basiccapabilities": [],
+        "systemio": ["allow_stdout"],
+        "read_fds": [3, 5],
+        "write_fds": [4],
+    }
+    assert "read_fds" not in pyextrasafe.SafetyContext().effective_flags()
@@ -0,0 +1,19 @@
+import pyextrasafe
+
+
+def test_freeze_does_not_modify_the_enabled_rule_sets():
+    io = pyextrasafe.SystemIO().allow_stdout()
+    ctx = pyextrasafe.SafetyContext().enable(io).freeze()
+    digest = ctx.filter_digest()
+
+    # The caller's rule set is not frozen, so it is modified in place.
+    assert io.allow_stderr() is io
+    assert ctx.filter_digest() == digest
+
+
+def test_frozen_context_contains_frozen_copies():
+    io = pyextrasafe.SystemIO().allow_stdout()
+    ctx = pyextrasafe.SafetyContext().enable(io).freeze()
+    (copy,) = ctx
+    assert copy is not io
+    assert copy.allow_stderr() is not copy
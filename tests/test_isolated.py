@@ -0,0 +1,29 @@
+import os
+
+import pytest
+
+import pyextrasafe
+
+
+def test_result_is_returned():
+    ctx = pyextrasafe.SafetyContext()
+    assert pyextrasafe.run_isolated(ctx, sum, [1, 2, 3]) == 6
+
+
+def test_exception_is_reraised():
+    ctx = pyextrasafe.SafetyContext()
+    with pytest.raises(PermissionError):
+        pyextrasafe.run_isolated(ctx, os.listdir, ".")
+
+
+def test_parent_is_not_sandboxed():
+    ctx = pyextrasafe.SafetyContext()
+    with pytest.raises(PermissionError):
+        pyextrasafe.run_isolated(ctx, os.listdir, ".")
+    assert os.listdir(".") is not None
+
+
+def test_forbidden_syscall_is_named():
+    ctx = pyextrasafe.SafetyContext(on_violation="kill_process")
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="getppid"):
+        pyextrasafe.run_isolated(ctx, os.getppid)
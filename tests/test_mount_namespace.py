@@ -0,0 +1,23 @@
+import pyextrasafe
+
+
+def test_flag_needs_confirmation():
+    mounts = pyextrasafe.MountNamespace()
+    confirmation = mounts.allow_mount()
+    assert not isinstance(confirmation, pyextrasafe.RuleSet)
+    assert mounts.flags() == []
+    assert confirmation.yes_really() is mounts
+    assert mounts.flags() == ["allow_mount"]
+
+
+def test_flags_cannot_be_set_without_confirmation():
+    for name in ["allow_mount", "allow_umount", "allow_pivot_root"]:
+        assert not hasattr(pyextrasafe.MountNamespace, "_" + name)
+
+
+def test_confirmation_of_frozen_rule_set_returns_copy():
+    mounts = pyextrasafe.MountNamespace().freeze()
+    copy = mounts.allow_umount().yes_really()
+    assert copy is not mounts
+    assert mounts.flags() == []
+    assert copy.flags() == ["allow_umount"]
@@ -0,0 +1,57 @@
+import socket
+
+import pytest
+
+import pyextrasafe
+
+
+def test_grouped_flags_set_primitive_flags():
+    flags = pyextrasafe.Networking().allow_start_tcp_clients().flags()
+    assert "allow_start_tcp_clients" in flags
+    assert "allow_connect" in flags
+    assert "allow_accept" in flags
+    assert "allow_socket" not in flags
+
+
+def test_grouped_flags_keep_their_bits():
+    networking = pyextrasafe.Networking().allow_running_tcp_clients()
+    copy = pyextrasafe.RuleSet.from_flag_bits("Networking", networking.flag_bits())
+    assert copy.flags() == networking.flags()
+
+
+def unix_socket():
+    return socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM).detach()
+
+
+def test_socket_composes_with_start():
+    networking = pyextrasafe.Networking().allow_socket().allow_start_tcp_clients()
+    ctx = pyextrasafe.SafetyContext().enable(networking)
+    # allow_start_tcp_clients() only allows TCP sockets, allow_socket() allows all.
+    assert pyextrasafe.run_isolated(ctx, unix_socket) >= 0
+
+
+def test_start_restricts_socket():
+    networking = pyextrasafe.Networking().allow_start_tcp_clients()
+    ctx = pyextrasafe.SafetyContext().enable(networking)
+    with pytest.raises(PermissionError):
+        pyextrasafe.run_isolated(ctx, unix_socket)
+
+
+def test_grouped_flags_allow_fd_passing():
+    networking = pyextrasafe.Networking().allow_running_unix_clients()
+    assert "allow_fd_passing" in networking.flags()
+
+
+def test_fd_passing_needs_confirmation():
+    networking = pyextrasafe.Networking()
+    confirmation = networking.allow_fd_passing()
+    assert not isinstance(confirmation, pyextrasafe.RuleSet)
+    assert networking.flags() == []
+    assert confirmation.yes_really() is networking
+    assert networking.flags() == ["allow_fd_passing"]
+
+
+def test_fd_passing_cannot_be_set_from_bits():
+    bits = pyextrasafe.Networking().allow_fd_passing().yes_really().flag_bits()
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="confirmation"):
+        pyextrasafe.RuleSet.from_flag_bits("Networking", bits)
@@ -0,0 +1,37 @@
+import pytest
+
+import pyextrasafe
+
+CODE = """
+import os
+import pyextrasafe
+
+ctx = pyextrasafe.SafetyContext().enable(
+    pyextrasafe.SystemIO().allow_open_readonly().allow_close().allow_stdout()
+).notify(["openat"])
+ctx.apply_with_handler(lambda nr, args: {decision})
+try:
+    os.close(os.open("/", os.O_RDONLY))
+except PermissionError:
+    print("denied")
+else:
+    print("allowed")
+"""
+
+
+@pytest.mark.parametrize(
+    "decision, expected",
+    [
+        ("pyextrasafe.CONTINUE", "allowed"),
+        ("None", "denied"),
+        ("0", "denied"),
+    ],
+)
+def test_only_continue_allows_the_syscall(run_python, decision, expected):
+    result = run_python(CODE.format(decision=decision))
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.split() == [expected]
+
+
+def test_continue_has_a_repr():
+    assert repr(pyextrasafe.CONTINUE) == "pyextrasafe.CONTINUE"
@@ -0,0 +1,35 @@
+import os
+import sys
+import time
+
+import pyextrasafe
+
+
+def read_pid_file(path, **kwargs):
+    with pyextrasafe.lock_pid_file(path, closefd=True, **kwargs):
+        with open(path) as f:
+            return f.read().splitlines()
+
+
+def test_pid_file_contains_pid(tmp_path):
+    assert read_pid_file(tmp_path / "pid") == [str(os.getpid())]
+
+
+def test_pid_file_metadata_has_defaults(tmp_path):
+    before = int(time.time())
+    lines = read_pid_file(tmp_path / "pid", metadata={})
+    assert lines[0] == str(os.getpid())
+    assert lines[1].startswith("start=")
+    assert before <= int(lines[1][len("start=") :]) <= time.time()
+    assert lines[2] == f"cmd={sys.argv[0]}"
+    assert len(lines) == 3
+
+
+def test_pid_file_metadata_has_extra_keys(tmp_path):
+    lines = read_pid_file(tmp_path / "pid", metadata={"port": 8080, "cmd": "server"})
+    assert lines[2:] == ["cmd=server", "port=8080"]
+
+
+def test_pid_file_contents_take_precedence(tmp_path):
+    lines = read_pid_file(tmp_path / "pid", contents=b"x\n", metadata={"port": 1})
+    assert lines == ["x"]
@@ -0,0 +1,47 @@
+import signal
+
+import pytest
+
+import pyextrasafe
+
+
+def test_sandboxed_abort_terminates(run_python):
+    result = run_python(
+        """
+        import os
+        import pyextrasafe
+
+        ctx = pyextrasafe.SafetyContext().enable(
+            pyextrasafe.ProcessControl().allow_abort(),
+        )
+        ctx.apply_to_all_threads()
+        os.abort()
+        """
+    )
+    assert result.returncode == -signal.SIGABRT
+
+
+def test_madvise_advice_needs_a_context_without_basics():
+    control = pyextrasafe.ProcessControl().allow_madvise_advice(4)
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="BasicCapabilities"):
+        pyextrasafe.SafetyContext().enable(control).filter_digest()
+
+    without_advice = pyextrasafe.SafetyContext(include_basics=False)
+    with_advice = pyextrasafe.SafetyContext(include_basics=False).enable(control)
+    assert with_advice.filter_digest() != without_advice.filter_digest()
+
+
+def test_getrandom_flags_need_a_context_without_basics():
+    control = pyextrasafe.ProcessControl().allow_getrandom_flags(1)
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="getrandom"):
+        pyextrasafe.SafetyContext().enable(control).filter_digest()
+
+    blocking = pyextrasafe.ProcessControl().allow_getrandom_flags(0)
+    nonblocking = pyextrasafe.SafetyContext(include_basics=False).enable(control)
+    both = pyextrasafe.SafetyContext(include_basics=False).enable(control, blocking)
+    assert both.filter_digest() != nonblocking.filter_digest()
+
+
+def test_madvise_advice_must_not_be_negative():
+    with pytest.raises(pyextrasafe.ExtraSafeError, match="illegal madvise advice"):
+        pyextrasafe.ProcessControl().allow_madvise_advice(-1)
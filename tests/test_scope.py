@@ -0,0 +1,42 @@
+import ctypes
+import errno
+
+import pyextrasafe
+
+
+LIBC = ctypes.CDLL(None, use_errno=True)
+
+
+def native_getppid():
+    # The syscall is made by libc, without going through Python's os module.
+    ctypes.set_errno(0)
+    return LIBC.getppid(), ctypes.get_errno()
+
+
+def test_explain_scope_mentions_native_code():
+    assert "native" in pyextrasafe.explain_scope()
+
+
+def test_native_syscall_is_allowed_without_filter():
+    ppid, err = native_getppid()
+    assert ppid > 0
+    assert err == 0
+
+
+def test_native_syscall_is_governed_by_filter():
+    # BasicCapabilities don't include getppid, so the filter denies it with EPERM.
+    ctx = pyextrasafe.SafetyContext()
+    assert pyextrasafe.run_isolated(ctx, native_getppid) == (-1, errno.EPERM)
+
+
+def native_getpid():
+    ctypes.set_errno(0)
+    return LIBC.getpid(), ctypes.get_errno()
+
+
+def test_native_syscall_is_allowed_by_filter():
+    # BasicCapabilities include getpid.
+    ctx = pyextrasafe.SafetyContext()
+    pid, err = pyextrasafe.run_isolated(ctx, native_getpid)
+    assert pid > 0
+    assert err == 0
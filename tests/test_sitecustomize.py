@@ -0,0 +1,35 @@
+import os
+
+import pyextrasafe
+
+
+def run_with_hook(run_python, directory, code):
+    env = dict(os.environ, PYTHONPATH=str(directory))
+    return run_python(code, env=env)
+
+
+def test_startup_safe_runs_pass(run_python, tmp_path):
+    ctx = pyextrasafe.SafetyContext.startup_safe()
+    pyextrasafe.install_sitecustomize(ctx, tmp_path)
+    result = run_with_hook(run_python, tmp_path, "pass")
+    assert result.returncode == 0, result.stderr
+
+
+def test_startup_safe_runs_imports_and_prints(run_python, tmp_path):
+    ctx = pyextrasafe.SafetyContext.startup_safe()
+    pyextrasafe.install_sitecustomize(ctx, tmp_path)
+    result = run_with_hook(
+        run_python,
+        tmp_path,
+        """
+        import json
+        import socket
+
+        try:
+            socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+        except PermissionError:
+            print(json.dumps("sandboxed"))
+        """,
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.strip() == '"sandboxed"'
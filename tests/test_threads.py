@@ -0,0 +1,83 @@
+import ctypes
+import errno
+import threading
+
+import pyextrasafe
+
+LIBC = ctypes.CDLL(None, use_errno=True)
+THREAD_FUNC = ctypes.CFUNCTYPE(ctypes.c_void_p, ctypes.c_void_p)
+# clone3 has the same number on x86, x86-64, ARM, and RISC-V.
+SYS_CLONE3 = 435
+
+
+def start_thread(target=lambda: None):
+    thread = threading.Thread(target=target)
+    thread.start()
+    return thread
+
+
+def thread_terminates():
+    thread = start_thread()
+    thread.join(timeout=10)
+    return thread.is_alive()
+
+
+def native_join():
+    # pthread_join() waits for the kernel to clear the tid of the thread when it exits.
+    func = THREAD_FUNC(lambda arg: None)
+    thread = ctypes.c_ulong()
+    err = LIBC.pthread_create(ctypes.byref(thread), None, func, None)
+    if err:
+        return err
+    return LIBC.pthread_join(thread, None)
+
+
+def clone3_errno():
+    # Without arguments the kernel rejects the call with EINVAL, if it is allowed.
+    ctypes.set_errno(0)
+    LIBC.syscall(SYS_CLONE3, None, 0)
+    return ctypes.get_errno()
+
+
+def test_clone3_is_allowed():
+    ctx = pyextrasafe.SafetyContext().enable(pyextrasafe.Threads().allow_create())
+    assert pyextrasafe.run_isolated(ctx, clone3_errno) in (errno.EINVAL, errno.ENOSYS)
+
+
+def test_clone3_is_denied_without_allow_create():
+    ctx = pyextrasafe.SafetyContext()
+    assert pyextrasafe.run_isolated(ctx, clone3_errno) == errno.EPERM
+
+
+def test_sandboxed_thread_terminates():
+    ctx = pyextrasafe.SafetyContext().enable(pyextrasafe.Threads().allow_create())
+    assert pyextrasafe.run_isolated(ctx, thread_terminates) is False
+
+
+def test_sandboxed_thread_can_be_joined():
+    ctx = pyextrasafe.SafetyContext().enable(pyextrasafe.Threads().allow_create())
+    assert pyextrasafe.run_isolated(ctx, native_join) == 0
+
+
+def test_apply_to_threads_restores_signal_handler(run_python):
+    result = run_python(
+        """
+        import signal
+        import threading
+        import pyextrasafe
+
+        received = []
+        signal.signal(signal.SIGRTMAX, lambda *args: received.append(args))
+
+        stop = threading.Event()
+        worker = threading.Thread(target=stop.wait)
+        worker.start()
+        pyextrasafe.SafetyContext().apply_to_threads([worker.native_id])
+        stop.set()
+        worker.join()
+
+        signal.raise_signal(signal.SIGRTMAX)
+        assert len(received) == 1, received
+        """
+    )
+    assert result.returncode == 0, result.stderr
@@ -0,0 +1,8 @@
+import pyextrasafe
+
+
+def test_adjtime_needs_confirmation():
+    time = pyextrasafe.Time()
+    assert not hasattr(time, "_allow_adjtime")
+    assert time.allow_adjtime().yes_really() is time
+    assert time.flags() == ["allow_adjtime"]